@@ -0,0 +1,237 @@
+use std::{ fmt::Display, fmt::Result as FmtResult, fmt::Formatter };
+
+use crate::poe_ninja::currency::CurrencyData;
+use crate::poe_ninja::divination::{ self, DivinationData, Reward, RewardKind };
+use crate::poe_ninja::item::{ ItemData, ItemType };
+use crate::poe_ninja::overview::{ Overview, OverviewLine };
+use crate::poe_ninja::variant::Selector;
+
+/// Item overviews a card reward can be found in.
+pub const REWARD_ITEM_TYPES: [ItemType; 9] = [
+    ItemType::UniqueWeapon,
+    ItemType::UniqueArmour,
+    ItemType::UniqueAccessory,
+    ItemType::UniqueFlask,
+    ItemType::UniqueJewel,
+    ItemType::UniqueTincture,
+    ItemType::UniqueRelic,
+    ItemType::UniqueMap,
+    ItemType::SkillGem
+];
+
+/// Item overviews listing stackable currency-like rewards, e.g. essences,
+/// which the `Currency` and `Fragment` overviews do not have.
+pub const CURRENCY_ITEM_TYPES: [ItemType; 11] = [
+    ItemType::Essence,
+    ItemType::Scarab,
+    ItemType::Fossil,
+    ItemType::Resonator,
+    ItemType::Oil,
+    ItemType::Incubator,
+    ItemType::DeliriumOrb,
+    ItemType::Omen,
+    ItemType::Artifact,
+    ItemType::Tattoo,
+    ItemType::Vial
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High
+}
+
+impl Confidence {
+    pub fn from_listings(listings: u32) -> Confidence {
+        match listings {
+            0..=9 => Confidence::Low,
+            10..=49 => Confidence::Medium,
+            _ => Confidence::High
+        }
+    }
+}
+
+impl Display for Confidence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Confidence::Low => write!(f, "low"),
+            Confidence::Medium => write!(f, "medium"),
+            Confidence::High => write!(f, "high")
+        }
+    }
+}
+
+/// A reward matched to a price.
+#[derive(Debug, Clone)]
+pub struct PricedReward {
+    pub name: String,
+    pub chaos_value: f32,
+    pub listing_count: u32
+}
+
+/// Looks card rewards up in already loaded overviews.
+pub struct RewardPricer<'a> {
+    /// The `Currency` and `Fragment` overviews.
+    pub currency: &'a [CurrencyData],
+    /// Overviews of `REWARD_ITEM_TYPES` and `CURRENCY_ITEM_TYPES`.
+    pub items: &'a [ItemData],
+    pub cards: &'a DivinationData
+}
+
+impl RewardPricer<'_> {
+    pub fn price(&self, reward: &Reward) -> Option<PricedReward> {
+        let single = match reward.kind {
+            RewardKind::Currency => self.price_currency(&reward.name),
+            RewardKind::Unique => self.price_unique(reward),
+            RewardKind::Gem => self.price_gem(reward),
+            RewardKind::Divination => self.cards.find(&reward.name).map(|line| {
+                Self::priced(&line)
+            }),
+            RewardKind::Item => None::<PricedReward>
+        }?;
+
+        return Some(PricedReward {
+            chaos_value: single.chaos_value * reward.quantity as f32,
+            ..single
+        });
+    }
+
+    fn priced<L: OverviewLine>(line: &L) -> PricedReward {
+        PricedReward {
+            name: line.name().to_string(),
+            chaos_value: line.chaos_value(),
+            listing_count: line.listing_count()
+        }
+    }
+
+    /// Tries the currency overviews first, then the item overviews for
+    /// rewards like essences or scarabs.
+    fn price_currency(&self, name: &str) -> Option<PricedReward> {
+        if name == "Chaos Orb" {
+            return Some(PricedReward {
+                name: name.to_string(),
+                chaos_value: 1.0,
+                listing_count: u32::MAX
+            });
+        }
+        for data in self.currency {
+            if let Some(line) = data.find(name) {
+                return Some(Self::priced(&line));
+            }
+        }
+        return self.price_item(name, &Selector::default());
+    }
+
+    /// The cheapest line named `name` that `selector` accepts.
+    fn price_item(&self, name: &str, selector: &Selector) -> Option<PricedReward> {
+        self.items.iter()
+            .flat_map(|data| data.variants(name, selector))
+            .min_by(|a, b| a.chaosValue.total_cmp(&b.chaosValue))
+            .map(|line| Self::priced(&line))
+    }
+
+    /// Unique rewards come unlinked and as the base variant, so lines with
+    /// links or a variant such as `Relic` are only used when nothing else
+    /// is listed.
+    fn price_unique(&self, reward: &Reward) -> Option<PricedReward> {
+        let selector = Selector { corrupted: Some(reward.corrupted), ..Selector::default() };
+        let plain = self.items.iter()
+            .flat_map(|data| data.variants(&reward.name, &selector))
+            .find(|line| line.links.is_none() && line.variant.is_none());
+
+        return plain.map(|line| Self::priced(&line))
+            .or_else(|| self.price_item(&reward.name, &selector));
+    }
+
+    /// Gem rewards read like `Level 3 Enlighten`, while poe.ninja names the
+    /// gem `Enlighten Support` and lists each level separately.
+    fn price_gem(&self, reward: &Reward) -> Option<PricedReward> {
+        let (level, name) = match reward.name.strip_prefix("Level ") {
+            Some(rest) => match rest.split_once(' ') {
+                Some((level, gem)) => (level.parse::<u32>().ok(), gem),
+                None => (None::<u32>, rest)
+            },
+            None => (None::<u32>, reward.name.as_str())
+        };
+        let selector = Selector {
+            gem_level: level,
+            corrupted: Some(reward.corrupted),
+            ..Selector::default()
+        };
+
+        return self.price_item(name, &selector)
+            .or_else(|| self.price_item(&format!("{} Support", name), &selector));
+    }
+}
+
+/// Expected value of turning in a full stack of a card.
+#[derive(Debug, Clone)]
+pub struct CardEv {
+    pub card: divination::Line,
+    pub reward: Reward,
+    pub reward_value: f32,
+    pub set_cost: f32,
+    pub profit: f32,
+    /// Profit relative to the cost of the set.
+    pub margin: f32,
+    pub confidence: Confidence
+}
+
+impl CardEv {
+    pub fn evaluate(card: &divination::Line, pricer: &RewardPricer) -> Option<CardEv> {
+        let reward = card.reward()?;
+        let priced = pricer.price(&reward)?;
+        let set_cost = card.set_value();
+        let profit = priced.chaos_value - set_cost;
+        let listings = card.listingCount.min(priced.listing_count);
+
+        return Some(CardEv {
+            card: card.clone(),
+            reward,
+            reward_value: priced.chaos_value,
+            set_cost,
+            profit,
+            margin: if set_cost > 0.0 { profit / set_cost } else { 0.0 },
+            confidence: Confidence::from_listings(listings)
+        });
+    }
+}
+
+impl Display for CardEv {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{}: {:.1}c ({}x {}c) -> {} {:.1}c, profit {:.1}c ({:.1}%) [{}]",
+            self.card.name,
+            self.set_cost,
+            self.card.stack_size(),
+            self.card.chaosValue,
+            self.reward,
+            self.reward_value,
+            self.profit,
+            self.margin * 100.0,
+            self.confidence
+        )
+    }
+}
+
+/// Evaluates every card with a priceable reward, best margin first. Cards
+/// whose reward could not be priced are returned separately.
+pub fn rank_cards(
+    cards: &DivinationData, pricer: &RewardPricer
+) -> (Vec<CardEv>, Vec<divination::Line>) {
+    let mut ranked = Vec::<CardEv>::new();
+    let mut unpriced = Vec::<divination::Line>::new();
+
+    for card in &cards.lines {
+        match CardEv::evaluate(card, pricer) {
+            Some(ev) => ranked.push(ev),
+            None => unpriced.push(card.clone())
+        }
+    }
+
+    ranked.sort_by(|a, b| b.margin.total_cmp(&a.margin));
+
+    return (ranked, unpriced);
+}
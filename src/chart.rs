@@ -0,0 +1,79 @@
+const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+fn bounds(values: &[Option<f32>]) -> Option<(f32, f32)> {
+    let mut known = values.iter().flatten();
+    let first = *known.next()?;
+
+    return Some(known.fold((first, first), |(lo, hi), &v| (lo.min(v), hi.max(v))));
+}
+
+/// Averages `values` down to at most `width` buckets, keeping gaps as `None`.
+fn resample(values: &[Option<f32>], width: usize) -> Vec<Option<f32>> {
+    if values.len() <= width || width == 0 {
+        return values.to_vec();
+    }
+
+    return (0..width).map(|i| {
+        let start = i * values.len() / width;
+        let end = ((i + 1) * values.len() / width).max(start + 1);
+        let bucket: Vec<f32> = values[start..end].iter().flatten().copied().collect();
+
+        if bucket.is_empty() {
+            None
+        } else {
+            Some(bucket.iter().sum::<f32>() / bucket.len() as f32)
+        }
+    }).collect();
+}
+
+/// One-row chart like `▁▃▅█`; missing values are left blank.
+pub fn sparkline(values: &[Option<f32>], width: usize) -> String {
+    let values = resample(values, width);
+    let (lo, hi) = match bounds(&values) {
+        Some(bounds) => bounds,
+        None => return String::new()
+    };
+    let span = hi - lo;
+
+    return values.iter().map(|value| match value {
+        Some(v) if span > 0.0 => {
+            TICKS[(((v - lo) / span) * (TICKS.len() - 1) as f32).round() as usize]
+        },
+        Some(_) => TICKS[TICKS.len() / 2],
+        None => ' '
+    }).collect();
+}
+
+/// Multi-row chart with the max and min value labelled on the left.
+pub fn line_chart(values: &[Option<f32>], width: usize, height: usize) -> Vec<String> {
+    let values = resample(values, width);
+    let (lo, hi) = match bounds(&values) {
+        Some(bounds) => bounds,
+        None => return Vec::new()
+    };
+    let span = if hi > lo { hi - lo } else { 1.0 };
+    let height = height.max(2);
+
+    let rows: Vec<Option<usize>> = values.iter().map(|value| {
+        value.map(|v| (((v - lo) / span) * (height - 1) as f32).round() as usize)
+    }).collect();
+
+    let mut lines = Vec::<String>::new();
+    for level in (0..height).rev() {
+        let label = if level == height - 1 {
+            format!("{:>10.1}", hi)
+        } else if level == 0 {
+            format!("{:>10.1}", lo)
+        } else {
+            " ".repeat(10)
+        };
+        let plot: String = rows.iter().map(|row| match row {
+            Some(row) if *row == level => '●',
+            Some(row) if *row > level => '│',
+            _ => ' '
+        }).collect();
+
+        lines.push(format!("{} ┤{}", label, plot));
+    }
+    return lines;
+}
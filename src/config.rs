@@ -0,0 +1,123 @@
+use std::{ env, fs, io, sync::OnceLock };
+
+use homedir::get_my_home;
+
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::poe_ninja::general::{ BASE_URL, CACHE_THRESHOLD };
+
+pub const USER_AGENT: &str = concat!("exilian/", env!("CARGO_PKG_VERSION"));
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings from `$XDG_CONFIG_HOME/exilian/config.toml` (or the file named
+/// by `EXILIAN_CONFIG`). Every key can be overridden by an `EXILIAN_*`
+/// variable, e.g. `EXILIAN_LEAGUE` or `EXILIAN_BASE_URL`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub league: Option<String>,
+    pub category: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    pub format: Option<String>,
+    /// Currency prices are shown in, e.g. `divine`.
+    pub unit: Option<String>,
+    pub cache_dir: Option<String>,
+    /// Minutes a cached overview is considered fresh.
+    pub cache_threshold: Option<i64>,
+    pub base_url: Option<String>,
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>
+}
+
+#[allow(unused)]
+impl Config {
+    pub fn path() -> Result<String, Error> {
+        if let Ok(path) = env::var("EXILIAN_CONFIG") {
+            return Ok(path);
+        }
+
+        let base = match env::var("XDG_CONFIG_HOME") {
+            Ok(dir) if !dir.is_empty() => dir,
+            _ => match get_my_home() {
+                Ok(Some(home)) => format!("{}/.config", home.to_string_lossy()),
+                _ => return Err(Error::CacheIo(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Could not determine the home directory"
+                )))
+            }
+        };
+
+        return Ok(format!("{}/exilian/config.toml", base));
+    }
+
+    /// Reads the config file, if there is one, and applies the environment.
+    pub fn load() -> Result<Config, Error> {
+        let mut config = match fs::read_to_string(Self::path()?) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| Error::Config(e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(Error::CacheIo(e))
+        };
+        config.apply_env()?;
+
+        return Ok(config);
+    }
+
+    fn apply_env(&mut self) -> Result<(), Error> {
+        let vars = [
+            ("EXILIAN_LEAGUE", &mut self.league),
+            ("EXILIAN_CATEGORY", &mut self.category),
+            ("EXILIAN_TYPE", &mut self.typ),
+            ("EXILIAN_FORMAT", &mut self.format),
+            ("EXILIAN_UNIT", &mut self.unit),
+            ("EXILIAN_CACHE_DIR", &mut self.cache_dir),
+            ("EXILIAN_BASE_URL", &mut self.base_url),
+            ("EXILIAN_PROXY", &mut self.proxy),
+            ("EXILIAN_USER_AGENT", &mut self.user_agent)
+        ];
+        for (name, field) in vars {
+            if let Ok(value) = env::var(name) {
+                *field = Some(value);
+            }
+        }
+
+        if let Ok(value) = env::var("EXILIAN_CACHE_THRESHOLD") {
+            self.cache_threshold = Some(value.parse().map_err(|_| {
+                Error::Config(format!("EXILIAN_CACHE_THRESHOLD is not a number: {}", value))
+            })?);
+        }
+
+        return Ok(());
+    }
+
+    pub fn base_url(&self) -> &str {
+        self.base_url.as_deref().unwrap_or(BASE_URL)
+    }
+
+    pub fn cache_threshold(&self) -> i64 {
+        self.cache_threshold.unwrap_or(CACHE_THRESHOLD)
+    }
+
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(USER_AGENT)
+    }
+}
+
+/// Loads the config for the rest of the process, reporting problems with
+/// it. Without a call to `init`, `get` loads it lazily and falls back to
+/// the defaults on error.
+pub fn init() -> Result<&'static Config, Error> {
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+    let config = Config::load()?;
+
+    return Ok(CONFIG.get_or_init(|| config));
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load().unwrap_or_default())
+}
@@ -0,0 +1,157 @@
+use std::{ fmt::Display, fmt::Result as FmtResult, fmt::Formatter };
+
+use crate::error::Error;
+use crate::poe_ninja::cache::CachePolicy;
+use crate::poe_ninja::currency::{ CurrencyData, CurrencyType };
+use crate::poe_ninja::general::League;
+use crate::poe_ninja::overview::{ Overview, OverviewLine };
+use crate::poe_ninja::search::{ Fields, MatchMode };
+
+pub const CHAOS_ORB: &str = "Chaos Orb";
+
+/// Short names accepted wherever a currency is expected.
+const ALIASES: [(&str, &str); 14] = [
+    ("c", CHAOS_ORB),
+    ("chaos", CHAOS_ORB),
+    ("d", "Divine Orb"),
+    ("div", "Divine Orb"),
+    ("divine", "Divine Orb"),
+    ("ex", "Exalted Orb"),
+    ("exa", "Exalted Orb"),
+    ("exalted", "Exalted Orb"),
+    ("mirror", "Mirror of Kalandra"),
+    ("shard", "Mirror Shard"),
+    ("alch", "Orb of Alchemy"),
+    ("fuse", "Orb of Fusing"),
+    ("vaal", "Vaal Orb"),
+    ("gcp", "Gemcutter's Prism")
+];
+
+/// Chaos value of one unit of a currency.
+#[derive(Debug, Clone)]
+pub struct Rate {
+    pub name: String,
+    pub chaos: f32
+}
+
+impl Rate {
+    pub fn chaos() -> Rate {
+        Rate { name: CHAOS_ORB.to_string(), chaos: 1.0 }
+    }
+
+    /// Expresses a chaos amount in this currency.
+    pub fn from_chaos(&self, chaos: f32) -> f32 {
+        chaos / self.chaos
+    }
+}
+
+/// Exchange rates for every currency in an overview, all relative to chaos.
+#[derive(Debug, Clone)]
+pub struct Rates {
+    data: CurrencyData,
+    rates: Vec<Rate>
+}
+
+#[allow(unused)]
+impl Rates {
+    /// The rate of a currency is the mean of what it is bought for
+    /// (`receive`) and sold for (`pay`), falling back to `chaosEquivalent`
+    /// when neither side has trades.
+    pub fn from_currency(data: CurrencyData) -> Rates {
+        let mut rates = vec![Rate::chaos()];
+
+        for line in &data.lines {
+            let sides: Vec<f32> = [line.buy_price(), line.sell_price()].into_iter()
+                .flatten()
+                .collect();
+            let chaos = if sides.is_empty() {
+                line.chaosEquivalent
+            } else {
+                sides.iter().sum::<f32>() / sides.len() as f32
+            };
+
+            if chaos > 0.0 {
+                rates.push(Rate { name: line.name().to_string(), chaos });
+            }
+        }
+
+        return Rates { data, rates };
+    }
+
+    /// Rates from the league's `Currency` overview.
+    pub async fn load(league: &League, policy: &CachePolicy) -> Result<Rates, Error> {
+        let loaded = CurrencyData::load(league, &CurrencyType::Currency, policy).await?;
+
+        return Ok(Self::from_currency(loaded.data));
+    }
+
+    pub fn rates(&self) -> &[Rate] {
+        &self.rates
+    }
+
+    /// Finds a currency by alias, by name ignoring case, or failing that by
+    /// the best fuzzy match on its name.
+    pub fn resolve(&self, unit: &str) -> Result<&Rate, Error> {
+        let unit = unit.trim();
+        let alias = ALIASES.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(unit));
+        let name = alias.map_or(unit, |(_, name)| name);
+
+        if let Some(rate) = self.rates.iter().find(|r| r.name.eq_ignore_ascii_case(name)) {
+            return Ok(rate);
+        }
+        if alias.is_some() {
+            return Err(Error::UnknownCurrency(name.to_string()));
+        }
+
+        let best = self.data.search(unit, &MatchMode::Fuzzy, Fields::Name).into_iter().next();
+        return best
+            .and_then(|(_, line)| self.rates.iter().find(|r| r.name == line.name()))
+            .ok_or_else(|| Error::UnknownCurrency(unit.to_string()));
+    }
+
+    /// `amount` with its unit replaced by the full currency name.
+    pub fn normalize(&self, amount: &Amount) -> Result<Amount, Error> {
+        let unit = self.resolve(&amount.unit)?;
+
+        return Ok(Amount { value: amount.value, unit: unit.name.clone() });
+    }
+
+    pub fn convert(&self, amount: &Amount, to: &str) -> Result<Amount, Error> {
+        let from = self.resolve(&amount.unit)?;
+        let to = self.resolve(to)?;
+
+        return Ok(Amount {
+            value: to.from_chaos(amount.value * from.chaos),
+            unit: to.name.clone()
+        });
+    }
+}
+
+/// A quantity of some currency such as `350c` or `1.5 divine`.
+#[derive(Debug, Clone)]
+pub struct Amount {
+    pub value: f32,
+    pub unit: String
+}
+
+impl Amount {
+    /// Parses a number followed by an optional unit, which defaults to chaos.
+    pub fn parse(s: &str) -> Result<Amount, Error> {
+        let s = s.trim();
+        let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+        let value = s[..split].parse::<f32>()
+            .map_err(|_| Error::InvalidAmount(s.to_string()))?;
+        let unit = s[split..].trim();
+
+        return Ok(Amount {
+            value,
+            unit: if unit.is_empty() { CHAOS_ORB.to_string() } else { unit.to_string() }
+        });
+    }
+}
+
+impl Display for Amount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:.2} {}", self.value, self.unit)
+    }
+}
@@ -0,0 +1,84 @@
+use std::{ fmt::Display, fmt::Result as FmtResult, fmt::Formatter, io };
+
+#[derive(Debug)]
+pub enum Error {
+    /// The request to poe.ninja could not be sent or its body not read.
+    Network(reqwest::Error),
+    /// poe.ninja answered with a non-success status code.
+    Status(u16),
+    /// poe.ninja answered, but the body is not the expected JSON.
+    Decode(serde_json::Error),
+    /// The cache directory or a cache file could not be read or written.
+    CacheIo(io::Error),
+    /// A cache file exists but does not parse.
+    CacheCorrupt(serde_json::Error),
+    /// The config file or an `EXILIAN_*` variable is invalid.
+    Config(String),
+    /// A cache file written with an unsupported schema version.
+    CacheVersion(u64),
+    /// Offline mode was requested but nothing is cached.
+    NotCached,
+    UnknownLeague(String),
+    UnknownType(String),
+    UnknownCurrency(String),
+    /// An outcome table refers to an item its overview does not list.
+    UnknownItem(String),
+    /// An age that is not a number followed by `m`, `h`, `d` or `w`.
+    InvalidAge(String),
+    /// A price that is not a number followed by a currency, e.g. `350c`.
+    InvalidAmount(String),
+    /// A search regex that does not compile.
+    InvalidPattern(String),
+    /// A variant selector term that is not `key=value` with a known key.
+    InvalidSelector(String),
+    /// An outcome table file that cannot be read or is malformed.
+    InvalidTable(String)
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::Network(e) => write!(f, "Error communicating with poe.ninja: {}", e),
+            Error::Status(code) => write!(f, "poe.ninja responded with status {}", code),
+            Error::Decode(e) => write!(f, "Malformed response from poe.ninja: {}", e),
+            Error::CacheIo(e) => write!(f, "Cache I/O error: {}", e),
+            Error::CacheCorrupt(e) => write!(f, "Corrupt cache file: {}", e),
+            Error::Config(s) => write!(f, "Invalid configuration: {}", s),
+            Error::CacheVersion(v) => write!(f, "Ignoring cache with unknown schema version {}", v),
+            Error::NotCached => write!(f, "Nothing cached to use offline"),
+            Error::UnknownLeague(s) => write!(f, "Unknown league: {}", s),
+            Error::UnknownType(s) => write!(f, "Unknown type: {}", s),
+            Error::UnknownCurrency(s) => write!(f, "Unknown currency: {}", s),
+            Error::UnknownItem(s) => write!(f, "Unknown item: {}", s),
+            Error::InvalidAge(s) => write!(f, "Invalid age: {}", s),
+            Error::InvalidAmount(s) => write!(f, "Invalid amount: {}", s),
+            Error::InvalidPattern(s) => write!(f, "Invalid search pattern: {}", s),
+            Error::InvalidSelector(s) => write!(f, "Invalid variant selector: {}", s),
+            Error::InvalidTable(s) => write!(f, "Invalid outcome table: {}", s)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            Error::CacheIo(e) => Some(e),
+            Error::CacheCorrupt(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Network(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::CacheIo(e)
+    }
+}
@@ -0,0 +1,440 @@
+use std::{ fmt::Display, fmt::Result as FmtResult, fmt::Formatter, fs, path::Path };
+
+use serde::Deserialize;
+
+use crate::convert::CHAOS_ORB;
+use crate::error::Error;
+use crate::poe_ninja::cache::CachePolicy;
+use crate::poe_ninja::currency::{ CurrencyData, CurrencyType };
+use crate::poe_ninja::general::League;
+use crate::poe_ninja::item::{ ItemData, ItemType };
+use crate::poe_ninja::overview::{ Overview, OverviewLine };
+use crate::poe_ninja::variant::Selector;
+
+/// Slack allowed when checking that chances add up to at most 1.
+const CHANCE_EPSILON: f64 = 1e-6;
+
+/// Something with a chaos value: either a fixed `chaos` amount or an item
+/// looked up by name in the overview of `type` (`Currency` by default).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ItemRef {
+    pub item: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    /// Variant selector terms such as `level=21` or `corrupted`.
+    pub select: Vec<String>,
+    pub quantity: Option<f64>,
+    pub chaos: Option<f64>
+}
+
+#[allow(unused)]
+impl ItemRef {
+    pub fn typ(&self) -> &str {
+        self.typ.as_deref().unwrap_or("Currency")
+    }
+
+    pub fn quantity(&self) -> f64 {
+        self.quantity.unwrap_or(1.0)
+    }
+
+    /// Chaos value of one unit in `data`, `None` when it is not listed.
+    pub fn price_in<O: Overview>(&self, data: &O) -> Result<Option<f64>, Error> {
+        let name = match &self.item {
+            Some(name) => name,
+            None => return Ok(None::<f64>)
+        };
+        let line = if self.select.is_empty() {
+            data.find(name)
+        } else {
+            data.variants(name, &Selector::parse(&self.select)?).into_iter().next()
+        };
+
+        return Ok(line.map(|line| line.chaos_value() as f64));
+    }
+}
+
+impl Display for ItemRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.quantity() != 1.0 {
+            write!(f, "{}x ", self.quantity())?;
+        }
+        match (&self.item, self.chaos) {
+            (Some(item), _) => write!(f, "{}", item)?,
+            (None, Some(chaos)) => write!(f, "{}c", chaos)?,
+            (None, None) => write!(f, "nothing")?
+        }
+        if !self.select.is_empty() {
+            write!(f, " ({})", self.select.join(" "))?;
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "RawOutcome")]
+pub struct Outcome {
+    pub value: ItemRef,
+    pub chance: f64
+}
+
+/// An `Outcome` as written in a table, with the `ItemRef` keys inline.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOutcome {
+    item: Option<String>,
+    #[serde(rename = "type")]
+    typ: Option<String>,
+    #[serde(default)]
+    select: Vec<String>,
+    quantity: Option<f64>,
+    chaos: Option<f64>,
+    chance: f64
+}
+
+impl From<RawOutcome> for Outcome {
+    fn from(raw: RawOutcome) -> Outcome {
+        Outcome {
+            value: ItemRef {
+                item: raw.item,
+                typ: raw.typ,
+                select: raw.select,
+                quantity: raw.quantity,
+                chaos: raw.chaos
+            },
+            chance: raw.chance
+        }
+    }
+}
+
+/// "Spend `cost`, get one of `outcomes`". Chances may add up to less than 1,
+/// in which case the rest is an outcome worth nothing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutcomeTable {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub cost: Vec<ItemRef>,
+    pub outcomes: Vec<Outcome>
+}
+
+#[allow(unused)]
+impl OutcomeTable {
+    /// Reads a table from a `.toml` or `.json` file.
+    pub fn load(path: &str) -> Result<OutcomeTable, Error> {
+        let invalid = |e: &dyn Display| Error::InvalidTable(format!("{}: {}", path, e));
+        let contents = fs::read_to_string(path).map_err(|e| invalid(&e))?;
+        let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+
+        let table: OutcomeTable = match extension {
+            Some("json") => serde_json::from_str(&contents).map_err(|e| invalid(&e))?,
+            _ => toml::from_str(&contents).map_err(|e| invalid(&e))?
+        };
+        table.validate().map_err(|e| invalid(&e))?;
+
+        return Ok(table);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.outcomes.is_empty() {
+            return Err("no outcomes".to_string());
+        }
+        if let Some(outcome) = self.outcomes.iter().find(|o| !(0.0..=1.0).contains(&o.chance)) {
+            return Err(format!("chance of {} is not between 0 and 1", outcome.value));
+        }
+        let total: f64 = self.outcomes.iter().map(|o| o.chance).sum();
+        if total > 1.0 + CHANCE_EPSILON {
+            return Err(format!("chances add up to {}", total));
+        }
+        return Ok(());
+    }
+
+    /// Every item the table refers to, cost first.
+    pub fn refs(&self) -> impl Iterator<Item = &ItemRef> {
+        self.cost.iter().chain(self.outcomes.iter().map(|o| &o.value))
+    }
+
+    /// Prices the cost and outcomes with `prices` and works out the odds.
+    pub fn evaluate(&self, prices: &Prices) -> Result<Evaluation, Error> {
+        let cost = self.cost.iter()
+            .try_fold(0.0, |total, r| Ok::<f64, Error>(total + prices.value(r)?))?;
+        let outcomes = self.outcomes.iter()
+            .map(|o| Ok((o.clone(), prices.value(&o.value)?)))
+            .collect::<Result<Vec<(Outcome, f64)>, Error>>()?;
+
+        return Ok(Evaluation::new(self.name.clone(), cost, outcomes));
+    }
+}
+
+/// Overviews loaded for the types an outcome table refers to.
+#[derive(Default)]
+pub struct Prices {
+    currency: Vec<(String, CurrencyData)>,
+    items: Vec<(String, ItemData)>
+}
+
+#[allow(unused)]
+impl Prices {
+    /// Loads every overview `table` needs. Types that name a currency
+    /// overview are read from it, anything else as an `ItemType`, including
+    /// raw types poe.ninja added since.
+    pub async fn load(
+        league: &League, table: &OutcomeTable, policy: &CachePolicy
+    ) -> Result<Prices, Error> {
+        let mut prices = Prices::default();
+
+        for r in table.refs().filter(|r| r.item.is_some()) {
+            let typ = r.typ();
+            if prices.has(typ) {
+                continue;
+            }
+
+            if let Some(currency_type) = CurrencyType::from(typ) {
+                let loaded = CurrencyData::load(league, &currency_type, policy).await?;
+                prices.currency.push((typ.to_string(), loaded.data));
+            } else {
+                let item_type = ItemType::from_raw(typ);
+                let loaded = ItemData::load(league, &item_type, policy).await?;
+                prices.items.push((typ.to_string(), loaded.data));
+            }
+        }
+        return Ok(prices);
+    }
+
+    fn has(&self, typ: &str) -> bool {
+        self.currency.iter().any(|(t, _)| t == typ) || self.items.iter().any(|(t, _)| t == typ)
+    }
+
+    /// Chaos value of `r`, times its quantity.
+    pub fn value(&self, r: &ItemRef) -> Result<f64, Error> {
+        let unit = match (&r.item, r.chaos) {
+            (Some(item), _) if item == CHAOS_ORB => Some(1.0),
+            (Some(_), _) => self.price(r)?,
+            (None, Some(chaos)) => Some(chaos),
+            (None, None) => Some(0.0)
+        };
+
+        return unit
+            .map(|unit| unit * r.quantity())
+            .ok_or_else(|| Error::UnknownItem(r.to_string()));
+    }
+
+    fn price(&self, r: &ItemRef) -> Result<Option<f64>, Error> {
+        let typ = r.typ();
+
+        if let Some((_, data)) = self.currency.iter().find(|(t, _)| t == typ) {
+            return r.price_in(data);
+        }
+        if let Some((_, data)) = self.items.iter().find(|(t, _)| t == typ) {
+            return r.price_in(data);
+        }
+        return Ok(None::<f64>);
+    }
+}
+
+/// Expected value and spread of a priced outcome table, in chaos.
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub name: Option<String>,
+    pub cost: f64,
+    /// Each outcome with its chaos value, including the implied worthless
+    /// one when the chances add up to less than 1.
+    pub outcomes: Vec<(Outcome, f64)>,
+    pub ev: f64,
+    pub variance: f64
+}
+
+#[allow(unused)]
+impl Evaluation {
+    pub fn new(name: Option<String>, cost: f64, mut outcomes: Vec<(Outcome, f64)>) -> Evaluation {
+        let rest = 1.0 - outcomes.iter().map(|(o, _)| o.chance).sum::<f64>();
+        if rest > CHANCE_EPSILON {
+            outcomes.push((Outcome { value: ItemRef::default(), chance: rest }, 0.0));
+        }
+
+        let ev: f64 = outcomes.iter().map(|(o, value)| o.chance * value).sum();
+        let variance = outcomes.iter()
+            .map(|(o, value)| o.chance * (value - ev).powi(2))
+            .sum();
+
+        return Evaluation { name, cost, outcomes, ev, variance };
+    }
+
+    pub fn profit(&self) -> f64 {
+        self.ev - self.cost
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// The most valuable outcome.
+    pub fn best(&self) -> Option<&(Outcome, f64)> {
+        self.outcomes.iter().max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Chance the best outcome would need for the EV to equal the cost, the
+    /// other outcomes keeping their relative odds. 0 when the rest already
+    /// covers the cost, `None` when not even a certain best outcome does.
+    pub fn break_even(&self) -> Option<f64> {
+        let (best, best_value) = self.best()?;
+        let rest_chance = 1.0 - best.chance;
+        let rest_ev = if rest_chance > CHANCE_EPSILON {
+            (self.ev - best.chance * best_value) / rest_chance
+        } else {
+            0.0
+        };
+
+        if *best_value <= rest_ev {
+            return None::<f64>;
+        }
+        let chance = (self.cost - rest_ev) / (best_value - rest_ev);
+
+        return Some(chance.max(0.0)).filter(|chance| *chance <= 1.0);
+    }
+}
+
+impl Display for Evaluation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if let Some(name) = &self.name {
+            writeln!(f, "{}", name)?;
+        }
+        writeln!(f, "cost {:.1}c", self.cost)?;
+        for (outcome, value) in &self.outcomes {
+            writeln!(f, "{:>7.2}%  {}: {:.1}c", outcome.chance * 100.0, outcome.value, value)?;
+        }
+        write!(
+            f,
+            "EV {:.1}c, profit {:.1}c, std dev {:.1}c",
+            self.ev,
+            self.profit(),
+            self.std_dev()
+        )?;
+        match (self.best(), self.break_even()) {
+            (Some((best, _)), Some(chance)) => {
+                write!(f, ", breaks even at {:.2}% {}", chance * 100.0, best.value)
+            },
+            _ => write!(f, ", never breaks even")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(chaos: f64, chance: f64) -> (Outcome, f64) {
+        let value = ItemRef { chaos: Some(chaos), ..ItemRef::default() };
+        return (Outcome { value, chance }, chaos);
+    }
+
+    fn write_table(name: &str, contents: &str) -> String {
+        let file = format!("exilian-ev-{}-{}", std::process::id(), name);
+        let path = std::env::temp_dir().join(file);
+        fs::write(&path, contents).unwrap();
+        return path.to_str().unwrap().to_string();
+    }
+
+    #[test]
+    fn missing_chance_is_a_worthless_outcome() {
+        let evaluation = Evaluation::new(None, 10.0, vec![outcome(100.0, 0.25)]);
+
+        assert_eq!(evaluation.outcomes.len(), 2);
+        let (rest, value) = &evaluation.outcomes[1];
+        assert_eq!(rest.chance, 0.75);
+        assert_eq!(*value, 0.0);
+        assert_eq!(evaluation.ev, 25.0);
+        assert_eq!(evaluation.variance, 0.25 * 75.0 * 75.0 + 0.75 * 25.0 * 25.0);
+        assert_eq!(evaluation.profit(), 15.0);
+    }
+
+    #[test]
+    fn full_chances_add_no_outcome() {
+        let evaluation = Evaluation::new(None, 0.0, vec![outcome(4.0, 0.5), outcome(2.0, 0.5)]);
+
+        assert_eq!(evaluation.outcomes.len(), 2);
+        assert_eq!(evaluation.ev, 3.0);
+        assert_eq!(evaluation.std_dev(), 1.0);
+    }
+
+    #[test]
+    fn break_even_scales_the_best_outcome() {
+        // The rest is worth 0, so 25c of cost needs a quarter of 100c.
+        let evaluation = Evaluation::new(None, 25.0, vec![outcome(100.0, 0.1)]);
+        assert_eq!(evaluation.break_even(), Some(0.25));
+    }
+
+    #[test]
+    fn break_even_is_zero_when_the_rest_covers_the_cost() {
+        let evaluation = Evaluation::new(None, 5.0, vec![outcome(100.0, 0.1), outcome(10.0, 0.9)]);
+        assert_eq!(evaluation.break_even(), Some(0.0));
+    }
+
+    #[test]
+    fn break_even_is_none_when_the_best_outcome_cannot_cover_the_cost() {
+        let dear = Evaluation::new(None, 200.0, vec![outcome(100.0, 0.1)]);
+        assert_eq!(dear.break_even(), None);
+
+        let flat = Evaluation::new(None, 5.0, vec![outcome(10.0, 0.5), outcome(10.0, 0.5)]);
+        assert_eq!(flat.break_even(), None);
+    }
+
+    #[test]
+    fn loads_toml_tables() {
+        let path = write_table("table.toml", r#"
+            name = "Alchemy"
+            cost = [{ item = "Orb of Alchemy", quantity = 2 }]
+
+            [[outcomes]]
+            item = "Divine Orb"
+            chance = 0.01
+
+            [[outcomes]]
+            item = "Headhunter"
+            type = "UniqueAccessory"
+            select = ["variant=relic"]
+            chance = 0.001
+        "#);
+        let table = OutcomeTable::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(table.name.as_deref(), Some("Alchemy"));
+        assert_eq!(table.cost[0].quantity(), 2.0);
+        assert_eq!(table.cost[0].typ(), "Currency");
+        assert_eq!(table.outcomes.len(), 2);
+        assert_eq!(table.outcomes[1].value.typ(), "UniqueAccessory");
+        assert_eq!(table.outcomes[1].value.select, vec!["variant=relic".to_string()]);
+    }
+
+    #[test]
+    fn loads_json_tables() {
+        let path = write_table("table.json", r#"{
+            "cost": [{ "chaos": 10 }],
+            "outcomes": [{ "chaos": 50, "chance": 0.2 }]
+        }"#);
+        let table = OutcomeTable::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let evaluation = table.evaluate(&Prices::default()).unwrap();
+        assert_eq!(evaluation.cost, 10.0);
+        assert_eq!(evaluation.ev, 10.0);
+    }
+
+    #[test]
+    fn rejects_invalid_tables() {
+        let over = write_table("over.json", r#"{
+            "outcomes": [{ "chaos": 1, "chance": 0.6 }, { "chaos": 2, "chance": 0.6 }]
+        }"#);
+        let unknown = write_table("unknown.toml", r#"
+            [[outcomes]]
+            chaos = 1
+            chance = 0.5
+            odds = 2
+        "#);
+
+        assert!(matches!(OutcomeTable::load(&over), Err(Error::InvalidTable(_))));
+        assert!(matches!(OutcomeTable::load(&unknown), Err(Error::InvalidTable(_))));
+        fs::remove_file(&over).unwrap();
+        fs::remove_file(&unknown).unwrap();
+    }
+}
@@ -1,48 +1,360 @@
-use argparse::{ArgumentParser, Store};
+use argparse::{ArgumentParser, List, Store, StoreOption, StoreTrue};
 
-mod poe_ninja;
-use poe_ninja::general::{ Category, League };
-use poe_ninja::currency::{ CurrencyData, CurrencyType };
-use poe_ninja::item::{ ItemData, ItemType };
+use exilian::cards::{ rank_cards, RewardPricer, CURRENCY_ITEM_TYPES, REWARD_ITEM_TYPES };
+use exilian::ev::{ OutcomeTable, Prices };
+use exilian::gems::{ rank_gems, Costs, GemRanking };
+use exilian::chart::{ line_chart, sparkline };
+use exilian::convert::{ Amount, Rate, Rates };
+use exilian::history::{ change_over, History, SeriesStats };
+use exilian::config;
+use exilian::log;
+use exilian::market::{ Depth, Graph, ROUND_TRIP };
+use exilian::poe_ninja::cache::{ self, CacheMode, CachePolicy };
+use exilian::poe_ninja::general::{ set_cache_dir, Category, League, LeagueIndex };
+use exilian::poe_ninja::currency::{ CurrencyData, CurrencyType };
+use exilian::poe_ninja::item::{ ItemData, ItemType };
+use exilian::poe_ninja::divination::{ DivinationData, DivinationType };
+use exilian::time::{ self, Zone };
+use exilian::output::{ Column, Format, DEFAULT_COLUMNS };
+use exilian::poe_ninja::overview::{ Overview, OverviewLine, TypeOf };
+use exilian::poe_ninja::query::{ Query, SortKey };
+use exilian::poe_ninja::search::{ Fields, MatchMode };
+use exilian::poe_ninja::variant::Selector;
+
+use chrono::Duration;
+
+const CHART_WIDTH: usize = 60;
+const CHART_HEIGHT: usize = 8;
+
+fn format_change(change: Option<f32>) -> String {
+    match change {
+        Some(change) => format!("{:+.1}%", change),
+        None => "n/a".to_string()
+    }
+}
+
+fn show_history<L: OverviewLine>(league: &League, typ: &L::Type, lines: &[L]) {
+    let series = match History::open::<L>(league, typ).and_then(|history| history.by_item()) {
+        Ok(series) => series,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    for line in lines {
+        let trend = line.sparkline();
+        println!("{} ({})", line.name(), line.details_id());
+        println!(
+            "  poe.ninja 7d: {} {:+.1}%",
+            sparkline(&trend.data, CHART_WIDTH),
+            trend.totalChange
+        );
+
+        let points = series.get(line.details_id()).map(Vec::as_slice).unwrap_or(&[]);
+        let stats = match SeriesStats::of(points) {
+            Some(stats) => stats,
+            None => {
+                println!("  No local history\n");
+                continue;
+            }
+        };
+
+        println!(
+            "  {} points from {} to {}",
+            points.len(),
+            time::display(&stats.first),
+            time::display(&stats.last)
+        );
+        println!("  min {:.1}c  max {:.1}c  mean {:.1}c", stats.min, stats.max, stats.mean);
+        println!(
+            "  1h {}  24h {}  7d {}",
+            format_change(change_over(points, Duration::hours(1))),
+            format_change(change_over(points, Duration::hours(24))),
+            format_change(change_over(points, Duration::days(7)))
+        );
+
+        let values: Vec<Option<f32>> = points.iter().map(|p| Some(p.chaos_value)).collect();
+        for row in line_chart(&values, CHART_WIDTH, CHART_HEIGHT) {
+            println!("  {}", row);
+        }
+        println!();
+    }
+}
 
 #[tokio::main]
-async fn get_item_data(op: &str, league: League, typ: ItemType, s: &str) {
-    let data = ItemData::load(&league, &typ).await;
-    
-    if data.updated.is_none() {
-       println!("No data to show"); 
-    } else if op == "prices" {
-        data.show_prices(s, false); 
-    } else if op == "prices-raw" {
-        data.show_prices(s, true); 
-    } else if op == "data" {
-        data.show()
+async fn get_league_index(policy: &CachePolicy) -> LeagueIndex {
+    LeagueIndex::load(policy).await
+}
+
+/// Output settings shared by the price listing operations.
+struct PriceOptions {
+    query: Query,
+    format: Format,
+    columns: Vec<Column>,
+    scores: bool,
+    /// Currency to price listings in; chaos when empty.
+    unit: String,
+    policy: CachePolicy
+}
+
+/// Loads an overview, noting on stderr where it came from.
+async fn load<O: Overview>(league: &League, typ: &TypeOf<O>, policy: &CachePolicy) -> Option<O> {
+    match O::load(league, typ, policy).await {
+        Ok(loaded) => {
+            eprintln!("{}: {}", typ, loaded.describe());
+            Some(loaded.data)
+        },
+        Err(e) => {
+            println!("{}: {}", typ, e);
+            None::<O>
+        }
     }
 }
 
 #[tokio::main]
-async fn get_currency_data(op: &str, league: League, typ: CurrencyType, s: &str) {
-    let data = CurrencyData::load(&league, &typ).await;
-    
-    if data.updated.is_none() { 
-       println!("No data to show"); 
+async fn get_data<O: Overview>(
+    op: &str, league: League, typ: TypeOf<O>, opts: &PriceOptions
+) {
+    let data = match load::<O>(&league, &typ, &opts.policy).await {
+        Some(data) => data,
+        None => return
+    };
+
+    let unit = if opts.unit.is_empty() {
+        Rate::chaos()
+    } else {
+        match Rates::load(&league, &opts.policy).await.and_then(|rates| rates.resolve(&opts.unit).cloned()) {
+            Ok(unit) => unit,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        }
+    };
+
+    if op == "prices" && opts.scores {
+        let query = &opts.query;
+        for (score, line) in data.search(&query.search, &query.mode, query.fields) {
+            println!("{:>8} {}", score, line.summary());
+        }
     } else if op == "prices" {
-        data.show_prices(s, false); 
+        data.show_prices(&opts.query, &opts.format, &opts.columns, &unit); 
+    } else if op == "variants" {
+        data.show_variants(&opts.query, &unit);
     } else if op == "prices-raw" {
-        data.show_prices(s, true); 
+        data.show_raw(&opts.query.search); 
     } else if op == "data" {
         data.show()
+    } else if op == "history" {
+        show_history(&league, &typ, &data.query(&opts.query));
+    }
+}
+
+#[tokio::main]
+async fn get_cards_ev(league: League, policy: &CachePolicy) {
+    let typ = DivinationType::DivinationCard;
+    let cards = match load::<DivinationData>(&league, &typ, policy).await {
+        Some(data) => data,
+        None => return
+    };
+
+    let mut currency = Vec::<CurrencyData>::new();
+    for typ in [CurrencyType::Currency, CurrencyType::Fragment] {
+        if let Some(data) = load::<CurrencyData>(&league, &typ, policy).await {
+            currency.push(data);
+        }
+    }
+
+    let mut items = Vec::<ItemData>::new();
+    for typ in REWARD_ITEM_TYPES.iter().chain(&CURRENCY_ITEM_TYPES) {
+        if let Some(data) = load::<ItemData>(&league, typ, policy).await {
+            items.push(data);
+        }
+    }
+
+    let pricer = RewardPricer { currency: &currency, items: &items, cards: &cards };
+    let (ranked, unpriced) = rank_cards(&cards, &pricer);
+
+    for ev in &ranked {
+        println!("{}", ev);
+    }
+    if !unpriced.is_empty() {
+        println!("\n{} cards with unpriced rewards", unpriced.len());
+    }
+}
+
+#[tokio::main]
+async fn get_gems_profit(
+    league: League, policy: &CachePolicy, ranking: GemRanking, query: &Query
+) {
+    let typ = CurrencyType::Currency;
+    let currency = match load::<CurrencyData>(&league, &typ, policy).await {
+        Some(data) => data,
+        None => return
+    };
+    let costs = match Costs::from_currency(&currency) {
+        Ok(costs) => costs,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    let typ = ItemType::SkillGem;
+    let gems = match load::<ItemData>(&league, &typ, policy).await {
+        Some(data) => data,
+        None => return
+    };
+
+    let ranked = rank_gems(&gems, &costs, ranking).into_iter()
+        .filter(|profit| query.min_listings.is_none_or(|min| profit.listings >= min));
+    for profit in ranked.skip(query.offset).take(query.limit.unwrap_or(usize::MAX)) {
+        println!("{}", profit);
+    }
+}
+
+#[tokio::main]
+async fn show_ev(league: League, policy: &CachePolicy, path: &str) {
+    let result = async {
+        let table = OutcomeTable::load(path)?;
+        let prices = Prices::load(&league, &table, policy).await?;
+
+        table.evaluate(&prices)
+    }.await;
+
+    match result {
+        Ok(evaluation) => println!("{}", evaluation),
+        Err(e) => println!("{}", e)
+    }
+}
+
+#[tokio::main]
+async fn show_depth(league: League, typ: CurrencyType, policy: &CachePolicy, query: &Query) {
+    let data = match load::<CurrencyData>(&league, &typ, policy).await {
+        Some(data) => data,
+        None => return
+    };
+
+    for line in data.query(query) {
+        println!("{}", Depth::of(&line));
+    }
+}
+
+#[tokio::main]
+async fn show_arbitrage(
+    league: League, typ: CurrencyType, policy: &CachePolicy, min_listings: u32
+) {
+    let data = match load::<CurrencyData>(&league, &typ, policy).await {
+        Some(data) => data,
+        None => return
+    };
+
+    let cycles = Graph::from_currency(&data, min_listings).profitable_cycles(ROUND_TRIP);
+    if cycles.is_empty() {
+        println!("No profitable round trips through chaos");
+    }
+    for cycle in cycles {
+        println!("{}", cycle);
+    }
+}
+
+#[tokio::main]
+async fn convert(league: League, policy: &CachePolicy, amount: &str, to: &str) {
+    let amount = match Amount::parse(amount) {
+        Ok(amount) => amount,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let rates = match Rates::load(&league, policy).await {
+        Ok(rates) => rates,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    match rates.normalize(&amount).and_then(|from| Ok((from, rates.convert(&amount, to)?))) {
+        Ok((from, converted)) => println!("{} = {}", from, converted),
+        Err(e) => println!("{}", e)
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1048576.0)
+    }
+}
+
+fn manage_cache(action: &str, league: Option<&League>, older_than: &str) {
+    let result = match action {
+        "ls" | "" => cache::entries().map(|entries| {
+            for entry in entries {
+                println!(
+                    "{}  {:>10}  {}",
+                    time::display(&entry.modified),
+                    format_size(entry.size),
+                    entry.path.display()
+                );
+            }
+        }),
+        "size" => cache::size().map(|size| println!("{}", format_size(size))),
+        "clear" => cache::clear(league).map(|removed| {
+            println!("Removed {} cached files", removed);
+        }),
+        "prune" if older_than.is_empty() => {
+            println!("'cache prune' needs --older-than");
+            return;
+        },
+        "prune" => cache::parse_age(older_than).and_then(cache::prune).map(|removed| {
+            for entry in &removed {
+                println!("Removed {}", entry.path.display());
+            }
+            println!("Removed {} cached files", removed.len());
+        }),
+        _ => {
+            println!("Invalid cache action: {}", action);
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        println!("{}", e);
     }
 }
 
 fn main() {
     // USAGE: exilian [operation] [-l league] [-c category] [- t type] [-s search_string]
     let mut op = String::new();
-    let mut query = String::new();
+    let mut list_query = String::new();
     let mut cat_str = String::new();
     let mut type_str = String::new();
     let mut search_str = String::new();
     let mut league_str = String::new();
+    let mut format_str = String::new();
+    let mut columns_str = String::new();
+    let mut sort_str = String::new();
+    let mut exact = false;
+    let mut ignore_case = false;
+    let mut regex = false;
+    let mut all_fields = false;
+    let mut scores = false;
+    let mut args = Vec::<String>::new();
+    let mut to_str = String::new();
+    let mut unit_str = String::new();
+    let mut refresh = false;
+    let mut offline = false;
+    let mut max_age = None::<i64>;
+    let mut max_stale = None::<i64>;
+    let mut cache_dir = String::new();
+    let mut older_than = String::new();
+    let mut utc = false;
+    let mut raw_type = false;
+    let mut query = Query::default();
 
     {
         let mut ap = ArgumentParser::new();
@@ -50,17 +362,32 @@ fn main() {
         ap.refer(&mut op).add_argument(
             "operation",
             Store,
-            "[prices(default), prices-raw, data, list]"
+            "[prices(default), prices-raw, data, history, variants, list, cards-ev, gems, ev, convert, depth, arbitrage, cache]. \
+             poe.ninja only reports trades against chaos, so 'arbitrage' finds chaos -> X -> chaos round trips"
         );
 
-        ap.refer(&mut query).add_option(
+        ap.refer(&mut args).add_argument(
+            "arguments",
+            List,
+            "Amount for 'convert' (e.g. 350c), [ls, clear, size, prune] for 'cache', profit for 'gems', an outcome table file (.toml or .json) for 'ev', or an item name and variant selectors (e.g. level=4 quality=20) for 'prices' and 'variants'"
+        );
+
+        ap.refer(&mut to_str).add_option(
+            &["--to"], Store, "Currency to convert to (default chaos)"
+        );
+
+        ap.refer(&mut unit_str).add_option(
+            &["--unit"], Store, "Currency to show prices in, e.g. divine"
+        );
+
+        ap.refer(&mut list_query).add_option(
             &["-q", "--query"],
             Store,
-            "Required for 'list' operation [categories, leagues, currency-types, item-types]"
+            "Required for 'list' operation [categories, leagues, currency-types, item-types, divination-types, formats, columns, sort-keys]"
         );
         
         ap.refer(&mut cat_str).add_option(
-            &["-c", "--category"], Store, "[Currency, Item]"
+            &["-c", "--category"], Store, "[Currency, Item, Divination]"
         );
         
         ap.refer(&mut type_str).add_option(
@@ -71,50 +398,365 @@ fn main() {
             &["-s", "--search"], Store, "Search string"
         );
         
+        ap.refer(&mut exact).add_option(
+            &["--exact"], StoreTrue, "Only show lines named exactly SEARCH"
+        );
+
+        ap.refer(&mut ignore_case).add_option(
+            &["-i", "--ignore-case"], StoreTrue, "Ignore case with --exact or --regex"
+        );
+
+        ap.refer(&mut regex).add_option(
+            &["--regex"], StoreTrue, "Treat SEARCH as a regular expression"
+        );
+
+        ap.refer(&mut all_fields).add_option(
+            &["--all-fields"], StoreTrue, "Also search base type, flavour text and modifiers"
+        );
+
+        ap.refer(&mut scores).add_option(
+            &["--scores"], StoreTrue, "Show search scores, best match first"
+        );
+
         ap.refer(&mut league_str).add_option(
             &["-l", "--league"], Store, "League"
         );
 
+        ap.refer(&mut format_str).add_option(
+            &["-f", "--format"], Store, "[plain(default), table, csv, tsv, json, ndjson]"
+        );
+
+        ap.refer(&mut columns_str).add_option(
+            &["--columns"],
+            Store,
+            "Comma separated [name, chaos, price, divine, exalted, listings, change, details-id, trade-id, icon]"
+        );
+
+        ap.refer(&mut sort_str).add_option(
+            &["--sort"], Store, "[name, chaos, divine, listings, change], or [profit, time] for 'gems'"
+        );
+
+        ap.refer(&mut query.reverse).add_option(
+            &["--reverse"], StoreTrue, "Reverse the sort direction (highest first by default)"
+        );
+
+        ap.refer(&mut query.min_price).add_option(
+            &["--min-price"], StoreOption, "Minimum chaos value"
+        );
+
+        ap.refer(&mut query.max_price).add_option(
+            &["--max-price"], StoreOption, "Maximum chaos value"
+        );
+
+        ap.refer(&mut query.min_listings).add_option(
+            &["--min-listings"], StoreOption, "Minimum listing count"
+        );
+
+        ap.refer(&mut query.select.links).add_option(
+            &["--links"], StoreOption, "Only items with this many linked sockets"
+        );
+
+        ap.refer(&mut query.select.gem_level).add_option(
+            &["--gem-level"], StoreOption, "Only gems of this level"
+        );
+
+        ap.refer(&mut query.select.gem_quality).add_option(
+            &["--quality"], StoreOption, "Only gems of this quality"
+        );
+
+        ap.refer(&mut query.select.map_tier).add_option(
+            &["--tier"], StoreOption, "Only maps of this tier"
+        );
+
+        ap.refer(&mut query.select.variant).add_option(
+            &["--variant"], StoreOption, "Only items of this variant, e.g. relic"
+        );
+
+        ap.refer(&mut query.limit).add_option(
+            &["--limit"], StoreOption, "Show at most LIMIT lines"
+        );
+
+        ap.refer(&mut query.offset).add_option(
+            &["--offset"], Store, "Skip the first OFFSET lines"
+        );
+
+        ap.refer(&mut refresh).add_option(
+            &["--refresh"], StoreTrue, "Pull from poe.ninja even if the cache is fresh"
+        );
+
+        ap.refer(&mut offline).add_option(
+            &["--offline"], StoreTrue, "Only use cached data, whatever its age"
+        );
+
+        ap.refer(&mut max_age).add_option(
+            &["--max-age"], StoreOption, "Minutes the cache is considered fresh (default: cache_threshold from the config)"
+        );
+
+        ap.refer(&mut max_stale).add_option(
+            &["--max-stale"], StoreOption, "Oldest cache in minutes to fall back on if a pull fails"
+        );
+
+        ap.refer(&mut cache_dir).add_option(
+            &["--cache-dir"], Store, "Cache directory, overriding config and environment"
+        );
+
+        ap.refer(&mut older_than).add_option(
+            &["--older-than"], Store, "Age for 'cache prune', e.g. 30m, 12h, 7d"
+        );
+
+        ap.refer(&mut raw_type).add_option(
+            &["--raw-type"], StoreTrue, "Send an item TYPE to poe.ninja even if it is not a known type"
+        );
+
+        ap.refer(&mut utc).add_option(
+            &["--utc"], StoreTrue, "Show times in UTC instead of local time"
+        );
+
         ap.parse_args_or_exit();
     }
+    let arg_str = args.first().cloned().unwrap_or_default();
+
+    let config = match config::init() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            config::get()
+        }
+    };
+    // `cache` works on every league unless one is named, so it must not
+    // pick up the configured league like the operations that fetch data.
+    let cache_league_str = league_str.clone();
+    let defaults = [
+        (&mut league_str, &config.league),
+        (&mut cat_str, &config.category),
+        (&mut type_str, &config.typ),
+        (&mut format_str, &config.format),
+        (&mut unit_str, &config.unit)
+    ];
+    for (arg, default) in defaults {
+        if let (true, Some(default)) = (arg.is_empty(), default) {
+            *arg = default.clone();
+        }
+    }
+
+    if !cache_dir.is_empty() {
+        set_cache_dir(&cache_dir);
+    }
+    if utc {
+        time::set_zone(Zone::Utc);
+    }
+
+    let mut policy = CachePolicy::default();
+    if offline {
+        policy.mode = CacheMode::Offline;
+    } else if refresh {
+        policy.mode = CacheMode::Refresh;
+    }
+    if let Some(minutes) = max_age {
+        policy.set_max_age(Duration::minutes(minutes));
+    }
+    policy.max_stale = max_stale.map(Duration::minutes);
+
+    log::set_hook(|event| eprintln!("{}", event));
     
-    if op == "" {
+    if op.is_empty() {
         op = "prices".to_string();
     }
 
     if op == "list" {
-        match query.as_str() {
+        match list_query.as_str() {
             "categories" => Category::show_all(),
-            "leagues" => League::show_all(),
+            "leagues" => League::show_all(&get_league_index(&policy)),
             "currency-types" => CurrencyType::show_all(),
             "item-types" => ItemType::show_all(),
-            _ => println!("Invalid query: {}", query)
+            "divination-types" => DivinationType::show_all(),
+            "formats" => Format::show_all(),
+            "columns" => Column::show_all(),
+            "sort-keys" => SortKey::show_all(),
+            _ => println!("Invalid query: {}", list_query)
         } 
 
-    } else if ["prices", "prices-raw", "data"].contains(&op.as_str()) {
-        let (league_found, league) = League::from_or_default(&league_str);
+    } else if op == "cards-ev" {
+        let index = get_league_index(&policy);
+        let (league_found, league) = League::from_or_default(&league_str, &index);
+        if !league_found {
+            eprintln!("Using default league: {}", league);
+        }
+
+        get_cards_ev(league, &policy);
+
+    } else if op == "gems" {
+        if arg_str != "profit" {
+            println!("Invalid gems operation: {}", arg_str);
+            return;
+        }
+        let ranking = match GemRanking::from(&sort_str) {
+            Some(ranking) => ranking,
+            None if sort_str.is_empty() => GemRanking::Profit,
+            None => {
+                println!("Invalid sort key: {}", sort_str);
+                return;
+            }
+        };
+        let index = get_league_index(&policy);
+        let (league_found, league) = League::from_or_default(&league_str, &index);
+        if !league_found {
+            eprintln!("Using default league: {}", league);
+        }
+
+        get_gems_profit(league, &policy, ranking, &query);
+
+    } else if op == "ev" {
+        if arg_str.is_empty() {
+            println!("'ev' needs an outcome table file");
+            return;
+        }
+        let index = get_league_index(&policy);
+        let (league_found, league) = League::from_or_default(&league_str, &index);
+        if !league_found {
+            eprintln!("Using default league: {}", league);
+        }
+
+        show_ev(league, &policy, &arg_str);
+
+    } else if op == "depth" || op == "arbitrage" {
+        let index = get_league_index(&policy);
+        let (league_found, league) = League::from_or_default(&league_str, &index);
+        if !league_found {
+            eprintln!("Using default league: {}", league);
+        }
+        let (found, typ) = CurrencyType::from_or_default(&type_str);
+        if !found {
+            eprintln!("Using default currency type: {}", typ);
+        }
+
+        if op == "depth" {
+            query.search = search_str;
+            show_depth(league, typ, &policy, &query);
+        } else {
+            show_arbitrage(league, typ, &policy, query.min_listings.unwrap_or(0));
+        }
+
+    } else if op == "cache" {
+        // Resolve the name like the fetching operations do, from the cached
+        // index only, so it maps to the same directory.
+        let league = if cache_league_str.is_empty() {
+            None::<League>
+        } else {
+            let index = get_league_index(&CachePolicy { mode: CacheMode::Offline, ..policy });
+            Some(League::from_or_default(&cache_league_str, &index).1)
+        };
+        manage_cache(&arg_str, league.as_ref(), &older_than);
+
+    } else if op == "convert" {
+        let index = get_league_index(&policy);
+        let (league_found, league) = League::from_or_default(&league_str, &index);
         if !league_found {
-            println!("Using default league: {}", league.to_string());
+            eprintln!("Using default league: {}", league);
+        }
+        if to_str.is_empty() {
+            to_str = "chaos".to_string();
+        }
+
+        // `1.5 divine` arrives as two arguments.
+        convert(league, &policy, &args.join(" "), &to_str);
+
+    } else if ["prices", "prices-raw", "data", "history", "variants"].contains(&op.as_str()) {
+        let index = get_league_index(&policy);
+        let (league_found, league) = League::from_or_default(&league_str, &index);
+        if !league_found {
+            eprintln!("Using default league: {}", league);
         }
 
+        let format = match Format::from(&format_str) {
+            Some(format) => format,
+            None if format_str.is_empty() => Format::from_or_default(&format_str).1,
+            None => {
+                println!("Invalid format: {}", format_str);
+                return;
+            }
+        };
+        if columns_str.is_empty() && !unit_str.is_empty() {
+            columns_str = DEFAULT_COLUMNS.replace("chaos", "price");
+        } else if columns_str.is_empty() {
+            columns_str = DEFAULT_COLUMNS.to_string();
+        }
+        let columns = match Column::parse_list(&columns_str) {
+            Ok(columns) => columns,
+            Err(_) => {
+                println!("Invalid columns: {}", columns_str);
+                return;
+            }
+        };
+        if !sort_str.is_empty() {
+            query.sort = match SortKey::from(&sort_str) {
+                Some(key) => Some(key),
+                None => {
+                    println!("Invalid sort key: {}", sort_str);
+                    return;
+                }
+            };
+        }
+        // Positional arguments are selector terms and an item name, which is
+        // looked up exactly rather than searched for.
+        let (terms, name): (Vec<String>, Vec<String>) = args.into_iter()
+            .partition(|arg| Selector::is_term(arg));
+        for term in &terms {
+            if let Err(e) = query.select.add(term) {
+                println!("{}", e);
+                return;
+            }
+        }
+        if search_str.is_empty() && !name.is_empty() {
+            search_str = name.join(" ");
+            query.mode = MatchMode::Exact { ignore_case: true };
+        }
+        if regex {
+            query.mode = match MatchMode::regex(&search_str, ignore_case) {
+                Ok(mode) => mode,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+        } else if exact {
+            query.mode = MatchMode::Exact { ignore_case };
+        }
+        if all_fields {
+            query.fields = Fields::All;
+        }
+        query.search = search_str;
+        let opts = PriceOptions { query, format, columns, scores, unit: unit_str, policy };
+
         let (category_found, category) = Category::from_or_default(&cat_str);
         if !category_found {
-            println!("Using default category: {}", category.to_string());
+            eprintln!("Using default category: {}", category);
         }
 
         if category == Category::Currency { 
             let (found, typ) = CurrencyType::from_or_default(&type_str);
             if !found {
-                println!("Using default currency type: {}", typ.to_string());
+                eprintln!("Using default currency type: {}", typ);
             }
-            get_currency_data(&op, league, typ, &search_str);
+            get_data::<CurrencyData>(&op, league, typ, &opts);
 
         } else if category == Category::Item {
-            let (found, typ) = ItemType::from_or_default(&type_str);
+            let (found, typ) = if raw_type && !type_str.is_empty() {
+                (true, ItemType::from_raw(&type_str))
+            } else {
+                ItemType::from_or_default(&type_str)
+            };
+            if !found {
+                eprintln!("Using default item type: {}", typ);
+            }
+            get_data::<ItemData>(&op, league, typ, &opts);
+
+        } else if category == Category::Divination {
+            let (found, typ) = DivinationType::from_or_default(&type_str);
             if !found {
-                println!("Using default item type: {}",typ.to_string());
+                eprintln!("Using default divination type: {}", typ);
             }
-            get_item_data(&op, league, typ, &search_str);
+            get_data::<DivinationData>(&op, league, typ, &opts);
 
         } else {
             println!("Invalid category: {}", cat_str);
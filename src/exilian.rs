@@ -1,3 +1,21 @@
-fn main() {
+use exilian::config;
+use exilian::poe_ninja::cache::CachePolicy;
+use exilian::poe_ninja::general::{ League, LeagueIndex };
+
+#[tokio::main]
+async fn main() {
+    let config = match config::init() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            config::get()
+        }
+    };
+    let index = LeagueIndex::load(&CachePolicy::default()).await;
+
     println!("Exilian");
+    println!("Current league: {}", index.current_league());
+    if let Some(league) = &config.league {
+        println!("Default league: {}", League::from_name(league));
+    }
 }
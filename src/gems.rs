@@ -0,0 +1,303 @@
+use std::{ fmt::Display, fmt::Result as FmtResult, fmt::Formatter };
+
+use crate::cards::Confidence;
+use crate::error::Error;
+use crate::ev::{ Evaluation, ItemRef, Outcome };
+use crate::poe_ninja::currency::CurrencyData;
+use crate::poe_ninja::item::{ self, ItemData };
+use crate::poe_ninja::overview::{ Overview, OverviewLine };
+
+pub const GEMCUTTERS_PRISM: &str = "Gemcutter's Prism";
+pub const VAAL_ORB: &str = "Vaal Orb";
+
+/// Quality gained per Gemcutter's Prism.
+const QUALITY_PER_PRISM: u32 = 1;
+
+/// Rough odds of a Vaal Orb on a gem: a quarter of outcomes move the level
+/// by one either way, and only a small part of the quality changes lands on
+/// +3. Everything else leaves a plain corrupted gem.
+const LEVEL_UP_CHANCE: f64 = 1.0 / 8.0;
+const QUALITY_UP_CHANCE: f64 = 1.0 / 24.0;
+
+/// Supports that only reach level 3 and need far more experience.
+const EXCEPTIONAL_GEMS: [&str; 3] = ["Enlighten Support", "Empower Support", "Enhance Support"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GemKind {
+    Regular,
+    Awakened,
+    Exceptional
+}
+
+impl GemKind {
+    pub fn of(name: &str) -> GemKind {
+        if name.starts_with("Awakened ") {
+            GemKind::Awakened
+        } else if EXCEPTIONAL_GEMS.contains(&name) {
+            GemKind::Exceptional
+        } else {
+            GemKind::Regular
+        }
+    }
+
+    /// Highest level reachable without corrupting.
+    pub fn max_level(&self) -> u32 {
+        match self {
+            GemKind::Regular => 20,
+            GemKind::Awakened => 5,
+            GemKind::Exceptional => 3
+        }
+    }
+
+    /// Rough experience from level 1 to `max_level`, standing in for the
+    /// leveling time. Only used to compare gems with each other, so the
+    /// figures need not be exact.
+    pub fn experience(&self) -> f32 {
+        match self {
+            GemKind::Regular => 342_000_000.0,
+            GemKind::Awakened => 1_934_000_000.0,
+            GemKind::Exceptional => 1_944_000_000.0
+        }
+    }
+}
+
+/// One level and quality combination a gem is listed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub level: u32,
+    pub quality: u32,
+    pub corrupted: bool
+}
+
+impl Target {
+    fn find<'a>(&self, gems: &'a ItemData, name: &str) -> Option<&'a item::Line> {
+        gems.lines.iter().find(|line| {
+            line.name == name
+                && line.gemLevel == Some(self.level)
+                && line.gemQuality.unwrap_or(0) == self.quality
+                && line.corrupted.unwrap_or(false) == self.corrupted
+        })
+    }
+
+    fn item_ref(&self, name: &str) -> ItemRef {
+        let mut select = vec![format!("level={}", self.level), format!("quality={}", self.quality)];
+        if self.corrupted {
+            select.push("corrupted".to_string());
+        }
+
+        return ItemRef { item: Some(name.to_string()), select, ..ItemRef::default() };
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}/{}", self.level, self.quality)?;
+        if self.corrupted {
+            write!(f, "c")?;
+        }
+        return Ok(());
+    }
+}
+
+/// Chaos prices of the currency spent on a gem.
+#[derive(Debug, Clone, Copy)]
+pub struct Costs {
+    pub prism: f32,
+    pub vaal: f32
+}
+
+impl Costs {
+    pub fn from_currency(currency: &CurrencyData) -> Result<Costs, Error> {
+        let price = |name: &str| {
+            currency.find(name)
+                .map(|line| line.chaos_value())
+                .ok_or_else(|| Error::UnknownCurrency(name.to_string()))
+        };
+
+        return Ok(Costs { prism: price(GEMCUTTERS_PRISM)?, vaal: price(VAAL_ORB)? });
+    }
+}
+
+/// How `rank_gems` orders its results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GemRanking {
+    Profit,
+    /// Profit per experience spent leveling.
+    Time
+}
+
+impl GemRanking {
+    pub fn from(s: &str) -> Option<GemRanking> {
+        match s {
+            "profit" => Some(GemRanking::Profit),
+            "time" | "experience" => Some(GemRanking::Time),
+            _ => None::<GemRanking>
+        }
+    }
+}
+
+/// What is done with a gem once it is at max level and 20% quality.
+#[derive(Debug, Clone)]
+pub enum Plan {
+    /// Sell it as it is.
+    Sell,
+    /// Corrupt it and sell whatever comes out, priced by `Evaluation`.
+    Corrupt(Evaluation)
+}
+
+/// Buying a level 1 gem, leveling and qualitying it, then following `plan`.
+/// `sell` is the expected sale price, so corrupting is ranked by its odds
+/// rather than its best outcome.
+#[derive(Debug, Clone)]
+pub struct GemProfit {
+    pub name: String,
+    pub kind: GemKind,
+    pub plan: Plan,
+    pub buy: f32,
+    pub prisms: u32,
+    pub cost: f32,
+    pub sell: f32,
+    pub profit: f32,
+    /// Profit per 100M experience.
+    pub per_experience: f32,
+    /// The lower listing count of the bought and sold gem.
+    pub listings: u32,
+    pub confidence: Confidence
+}
+
+impl GemProfit {
+    /// Prices both plans for `buy` against the lines in `gems`. Selling is
+    /// left out when the 20% quality max level gem is not listed, and
+    /// corrupting when neither of its good outcomes is.
+    pub fn evaluate(buy: &item::Line, gems: &ItemData, costs: &Costs) -> Vec<GemProfit> {
+        let kind = GemKind::of(&buy.name);
+        let level = kind.max_level();
+        let leveled = Target { level, quality: 20, corrupted: false };
+        let level_up = Target { level: level + 1, quality: 20, corrupted: true };
+        let quality_up = Target { level, quality: 23, corrupted: true };
+        let plain = Target { level, quality: 20, corrupted: true };
+
+        let prisms = 20u32.saturating_sub(buy.gemQuality.unwrap_or(0)) / QUALITY_PER_PRISM;
+        let cost = buy.chaosValue + prisms as f32 * costs.prism;
+        let profit = |plan: Plan, cost: f32, sell: f32, listings: u32| {
+            let listings = buy.listingCount.min(listings);
+
+            GemProfit {
+                name: buy.name.clone(),
+                kind,
+                plan,
+                buy: buy.chaosValue,
+                prisms,
+                cost,
+                sell,
+                profit: sell - cost,
+                per_experience: (sell - cost) / kind.experience() * 1e8,
+                listings,
+                confidence: Confidence::from_listings(listings)
+            }
+        };
+        let mut profits = Vec::<GemProfit>::new();
+
+        if let Some(sell) = leveled.find(gems, &buy.name) {
+            profits.push(profit(Plan::Sell, cost, sell.chaosValue, sell.listingCount));
+        }
+
+        let good = [(level_up, LEVEL_UP_CHANCE), (quality_up, QUALITY_UP_CHANCE)];
+        let listed: Vec<(Target, f64, &item::Line)> = good.iter()
+            .filter_map(|(target, chance)| Some((*target, *chance, target.find(gems, &buy.name)?)))
+            .collect();
+        if !listed.is_empty() {
+            // Other outcomes sell as a plain corrupted gem, or for nothing
+            // when poe.ninja does not list one.
+            let fallback = plain.find(gems, &buy.name);
+            let fallback_value = fallback.map_or(0.0, |line| line.chaosValue as f64);
+            let listed_chance: f64 = listed.iter().map(|(_, chance, _)| chance).sum();
+
+            let mut outcomes: Vec<(Outcome, f64)> = listed.iter()
+                .map(|(target, chance, line)| {
+                    let outcome = Outcome { value: target.item_ref(&buy.name), chance: *chance };
+                    (outcome, line.chaosValue as f64)
+                })
+                .collect();
+            outcomes.push((
+                Outcome { value: plain.item_ref(&buy.name), chance: 1.0 - listed_chance },
+                fallback_value
+            ));
+
+            let cost = cost + costs.vaal;
+            let odds = Evaluation::new(None::<String>, cost as f64, outcomes);
+            let listings = listed.iter().map(|(_, _, line)| line.listingCount).min().unwrap_or(0);
+            let sell = odds.ev as f32;
+
+            profits.push(profit(Plan::Corrupt(odds), cost, sell, listings));
+        }
+        return profits;
+    }
+}
+
+impl Display for GemProfit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let max = GemKind::max_level(&self.kind);
+
+        let vaal = match self.plan {
+            Plan::Sell => "",
+            Plan::Corrupt(_) => " + Vaal Orb"
+        };
+
+        write!(
+            f,
+            "{} 1 -> {}/20{}: cost {:.1}c ({}c + {} prisms{}), sell {:.1}c, profit {:.1}c \
+             ({:.1}c per 100M xp) [{}]",
+            self.name,
+            max,
+            vaal,
+            self.cost,
+            self.buy,
+            self.prisms,
+            vaal,
+            self.sell,
+            self.profit,
+            self.per_experience,
+            self.confidence
+        )?;
+        if let Plan::Corrupt(odds) = &self.plan {
+            let outcomes: Vec<String> = odds.outcomes.iter()
+                .map(|(outcome, value)| {
+                    let target = outcome.value.select.join(" ");
+                    format!("{} {:.1}c at {:.1}%", target, value, outcome.chance * 100.0)
+                })
+                .collect();
+            write!(f, "\n    outcomes: {}, std dev {:.1}c", outcomes.join(", "), odds.std_dev())?;
+        }
+        return Ok(());
+    }
+}
+
+/// Evaluates every gem with an uncorrupted level 1 line, best first by
+/// `ranking`. Corrupting is ranked by its expected sale price.
+pub fn rank_gems(gems: &ItemData, costs: &Costs, ranking: GemRanking) -> Vec<GemProfit> {
+    let mut ranked = Vec::<GemProfit>::new();
+
+    for buy in &gems.lines {
+        if buy.gemLevel != Some(1) || buy.corrupted == Some(true) {
+            continue;
+        }
+        let cheaper = gems.lines.iter().any(|other| {
+            other.name == buy.name
+                && other.gemLevel == Some(1)
+                && other.corrupted != Some(true)
+                && other.chaosValue < buy.chaosValue
+        });
+        if cheaper {
+            continue;
+        }
+
+        ranked.extend(GemProfit::evaluate(buy, gems, costs));
+    }
+
+    match ranking {
+        GemRanking::Profit => ranked.sort_by(|a, b| b.profit.total_cmp(&a.profit)),
+        GemRanking::Time => ranked.sort_by(|a, b| b.per_experience.total_cmp(&a.per_experience))
+    }
+    return ranked;
+}
@@ -0,0 +1,157 @@
+use std::{ collections::HashMap, fs, fs::OpenOptions, io, io::Write };
+
+use chrono::{ DateTime, Duration, Utc };
+
+use serde::{ Deserialize, Serialize };
+use serde_json;
+
+use crate::error::Error;
+use crate::poe_ninja::general::{ get_user_data_path, League };
+use crate::poe_ninja::overview::OverviewLine;
+
+/// One line of one pull.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PricePoint {
+    pub time: DateTime<Utc>,
+    pub details_id: String,
+    pub chaos_value: f32,
+    pub listing_count: u32,
+    pub count: u32
+}
+
+/// Append-only price log of one overview, stored as one JSON point per line
+/// in `<data>/history/<league>/<kind>/<type>.ndjson`.
+pub struct History {
+    path: String
+}
+
+impl History {
+    pub fn open<L: OverviewLine>(league: &League, typ: &L::Type) -> Result<History, Error> {
+        let dir = format!(
+            "{}/history/{}/{}",
+            get_user_data_path()?,
+            league,
+            L::ENDPOINT.cache_dir()
+        );
+
+        return Ok(History { path: format!("{}/{}.ndjson", dir, typ) });
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Appends every line of a pull made now.
+    pub fn record<L: OverviewLine>(&self, lines: &[L]) -> Result<(), Error> {
+        self.record_at(Utc::now(), lines)
+    }
+
+    pub fn record_at<L: OverviewLine>(
+        &self, time: DateTime<Utc>, lines: &[L]
+    ) -> Result<(), Error> {
+        if let Some((dir, _)) = self.path.rsplit_once('/') {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut buf = String::new();
+        for line in lines {
+            let point = PricePoint {
+                time,
+                details_id: line.details_id().to_string(),
+                chaos_value: line.chaos_value(),
+                listing_count: line.listing_count(),
+                count: line.count()
+            };
+            buf.push_str(&serde_json::to_string(&point).unwrap());
+            buf.push('\n');
+        }
+
+        let mut f = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        f.write_all(buf.as_bytes())?;
+
+        return Ok(());
+    }
+
+    /// Every recorded point, oldest first. A missing log is empty; a line
+    /// cut short by an interrupted write is skipped.
+    pub fn points(&self) -> Result<Vec<PricePoint>, Error> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::CacheIo(e))
+        };
+
+        return Ok(contents.lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect());
+    }
+
+    /// Every recorded point grouped by `detailsId`, each series oldest
+    /// first. Reads the log once, for looking up many items.
+    pub fn by_item(&self) -> Result<HashMap<String, Vec<PricePoint>>, Error> {
+        let mut series = HashMap::<String, Vec<PricePoint>>::new();
+
+        for point in self.points()? {
+            series.entry(point.details_id.clone()).or_default().push(point);
+        }
+        for points in series.values_mut() {
+            points.sort_by_key(|point| point.time);
+        }
+        return Ok(series);
+    }
+
+    /// Points of one item (by `detailsId`) with `from <= time <= to`.
+    pub fn series(
+        &self, details_id: &str, from: DateTime<Utc>, to: DateTime<Utc>
+    ) -> Result<Vec<PricePoint>, Error> {
+        let mut points: Vec<PricePoint> = self.points()?
+            .into_iter()
+            .filter(|point| {
+                point.details_id == details_id && point.time >= from && point.time <= to
+            })
+            .collect();
+        points.sort_by_key(|point| point.time);
+
+        return Ok(points);
+    }
+}
+
+/// Summary of a series as returned by `History::series`.
+#[derive(Debug, Clone)]
+pub struct SeriesStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>
+}
+
+impl SeriesStats {
+    pub fn of(points: &[PricePoint]) -> Option<SeriesStats> {
+        let first = points.first()?;
+        let last = points.last()?;
+        let values = points.iter().map(|point| point.chaos_value);
+
+        return Some(SeriesStats {
+            min: values.clone().fold(f32::INFINITY, f32::min),
+            max: values.clone().fold(f32::NEG_INFINITY, f32::max),
+            mean: values.sum::<f32>() / points.len() as f32,
+            first: first.time,
+            last: last.time
+        });
+    }
+}
+
+/// Percent change from the newest point at least `window` older than the
+/// last point to the last point, or `None` when the series is too short.
+pub fn change_over(points: &[PricePoint], window: Duration) -> Option<f32> {
+    let last = points.last()?;
+    let base = points.iter()
+        .rev()
+        .find(|point| point.time <= last.time - window)?;
+
+    if base.chaos_value == 0.0 {
+        return None::<f32>;
+    }
+    return Some((last.chaos_value - base.chaos_value) / base.chaos_value * 100.0);
+}
@@ -0,0 +1,15 @@
+pub mod cards;
+pub mod chart;
+pub mod config;
+pub mod convert;
+pub mod error;
+pub mod ev;
+pub mod gems;
+pub mod history;
+pub mod log;
+pub mod market;
+pub mod output;
+pub mod poe_ninja;
+pub mod time;
+
+pub use error::Error;
@@ -0,0 +1,59 @@
+use std::{ fmt::Display, fmt::Result as FmtResult, fmt::Formatter, sync::RwLock };
+
+use chrono::{ DateTime, Utc };
+
+use crate::error::Error;
+use crate::time;
+
+/// Progress and fallback notices from the loaders. The library never prints
+/// these itself; register a hook with `set_hook` to see them.
+pub enum Event<'a> {
+    Pulling { uri: &'a str },
+    CacheOutOfDate,
+    CacheUnreadable { error: &'a Error },
+    CacheMigrated { path: &'a str },
+    UsingStaleCache { error: &'a Error, updated: DateTime<Utc> },
+    /// A pull succeeded but could not be added to the price history.
+    HistoryUnwritable { error: &'a Error }
+}
+
+impl Display for Event<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Event::Pulling { .. } => write!(f, "Pulling from poe.ninja..."),
+            Event::CacheOutOfDate => write!(f, "Cache is out of date..."),
+            Event::CacheUnreadable { error } => write!(f, "{}", error),
+            Event::CacheMigrated { path } => write!(f, "Migrated cache file {}", path),
+            Event::UsingStaleCache { error, updated } => {
+                write!(f, "{}\nUsing cache from {}", error, time::display(updated))
+            },
+            Event::HistoryUnwritable { error } => {
+                write!(f, "Could not record price history: {}", error)
+            }
+        }
+    }
+}
+
+type Hook = Box<dyn Fn(&Event) + Send + Sync>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+pub fn set_hook<F: Fn(&Event) + Send + Sync + 'static>(hook: F) {
+    if let Ok(mut current) = HOOK.write() {
+        *current = Some(Box::new(hook));
+    }
+}
+
+pub fn clear_hook() {
+    if let Ok(mut current) = HOOK.write() {
+        *current = None;
+    }
+}
+
+pub(crate) fn emit(event: Event) {
+    if let Ok(current) = HOOK.read() {
+        if let Some(hook) = current.as_ref() {
+            hook(&event);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    collections::HashMap,
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter
+};
+
+use crate::cards::Confidence;
+use crate::convert::CHAOS_ORB;
+use crate::poe_ninja::currency::{ CurrencyData, Line };
+use crate::poe_ninja::general::{ SparkLine, TransactionSummary };
+use crate::poe_ninja::overview::OverviewLine;
+
+/// One side of a currency's market, priced in chaos per unit.
+#[derive(Debug, Clone)]
+pub struct Side {
+    pub chaos: f32,
+    pub listing_count: u32,
+    pub low_confidence: bool
+}
+
+impl Side {
+    /// poe.ninja only fills the regular sparkline when it trusts the data,
+    /// so an empty one marks the side as low confidence, as do few listings.
+    fn new(chaos: f32, summary: &TransactionSummary, sparkline: &SparkLine) -> Side {
+        let thin = Confidence::from_listings(summary.listing_count) == Confidence::Low;
+
+        Side {
+            chaos,
+            listing_count: summary.listing_count,
+            low_confidence: thin || sparkline.data.iter().all(|v| v.is_none())
+        }
+    }
+}
+
+/// Buy and sell side of a currency.
+#[derive(Debug, Clone)]
+pub struct Depth {
+    pub name: String,
+    /// What buying one unit costs.
+    pub buy: Option<Side>,
+    /// What selling one unit returns.
+    pub sell: Option<Side>
+}
+
+impl Depth {
+    pub fn of(line: &Line) -> Depth {
+        Depth {
+            name: line.name().to_string(),
+            buy: line.buy_price().zip(line.receive.as_ref()).map(|(chaos, receive)| {
+                Side::new(chaos, receive, &line.receiveSparkLine)
+            }),
+            sell: line.sell_price().zip(line.pay.as_ref()).map(|(chaos, pay)| {
+                Side::new(chaos, pay, &line.paySparkLine)
+            })
+        }
+    }
+
+    /// Gap between buy and sell price relative to the buy price, in percent.
+    pub fn spread(&self) -> Option<f32> {
+        match (&self.buy, &self.sell) {
+            (Some(buy), Some(sell)) => Some((buy.chaos - sell.chaos) / buy.chaos * 100.0),
+            _ => None::<f32>
+        }
+    }
+
+    pub fn low_confidence(&self) -> bool {
+        [&self.buy, &self.sell].iter().any(|side| match side {
+            Some(side) => side.low_confidence,
+            None => true
+        })
+    }
+}
+
+impl Display for Depth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let side = |side: &Option<Side>| match side {
+            Some(side) => format!(
+                "{:.2}c ({}{})",
+                side.chaos,
+                side.listing_count,
+                if side.low_confidence { ", low" } else { "" }
+            ),
+            None => "-".to_string()
+        };
+        let spread = match self.spread() {
+            Some(spread) => format!("{:.1}%", spread),
+            None => "n/a".to_string()
+        };
+
+        write!(
+            f,
+            "{}: buy {} sell {} spread {}",
+            self.name,
+            side(&self.buy),
+            side(&self.sell),
+            spread
+        )
+    }
+}
+
+/// A trade from one currency into another. `rate` units of `to` are
+/// received per unit of `from`.
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub from: u32,
+    pub to: u32,
+    pub rate: f32,
+    pub listing_count: u32
+}
+
+impl Exchange {
+    /// A summary's `value` is the amount of its pay currency given per unit
+    /// of its get currency.
+    pub fn of(summary: &TransactionSummary) -> Option<Exchange> {
+        if summary.value <= 0.0 {
+            return None::<Exchange>;
+        }
+
+        return Some(Exchange {
+            from: summary.pay_currency_id,
+            to: summary.get_currency_id,
+            rate: 1.0 / summary.value,
+            listing_count: summary.listing_count
+        });
+    }
+}
+
+/// A chain of trades ending in the currency it started with.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub currencies: Vec<String>,
+    /// Units held at the end for every unit put in.
+    pub rate: f32,
+    /// Fewest listings on any of the trades.
+    pub listing_count: u32
+}
+
+impl Cycle {
+    pub fn profit(&self) -> f32 {
+        (self.rate - 1.0) * 100.0
+    }
+}
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} -> {}: {:+.2}% ({} listings)",
+            self.currencies.join(" -> "),
+            self.currencies[0],
+            self.profit(),
+            self.listing_count
+        )
+    }
+}
+
+/// Trades in a round trip through chaos. poe.ninja only reports trades
+/// between chaos and each currency, so the graph is a star around chaos and
+/// no longer cycle exists.
+pub const ROUND_TRIP: usize = 2;
+
+/// Trades between currencies, keyed by poe.ninja currency id. As poe.ninja
+/// prices everything against chaos, every edge starts or ends at it.
+pub struct Graph {
+    names: HashMap<u32, String>,
+    edges: HashMap<u32, Vec<Exchange>>
+}
+
+impl Graph {
+    /// Builds the graph from every pay and receive summary, skipping trades
+    /// with fewer than `min_listings` listings.
+    pub fn from_currency(data: &CurrencyData, min_listings: u32) -> Graph {
+        let mut names: HashMap<u32, String> = data.currencyDetails.iter()
+            .map(|detail| (detail.id, detail.name.clone()))
+            .collect();
+        let chaos = names.iter().find(|(_, name)| *name == CHAOS_ORB).map(|(id, _)| *id);
+        let mut edges = HashMap::<u32, Vec<Exchange>>::new();
+
+        for line in &data.lines {
+            for summary in [&line.pay, &line.receive].into_iter().flatten() {
+                let exchange = match Exchange::of(summary) {
+                    Some(exchange) if exchange.listing_count >= min_listings => exchange,
+                    _ => continue
+                };
+
+                // Name currencies missing from `currencyDetails` after the line.
+                let own = if Some(exchange.from) == chaos { exchange.to } else { exchange.from };
+                names.entry(own).or_insert_with(|| line.name().to_string());
+
+                edges.entry(exchange.from).or_default().push(exchange);
+            }
+        }
+
+        return Graph { names, edges };
+    }
+
+    pub fn name(&self, id: u32) -> String {
+        match self.names.get(&id) {
+            Some(name) => name.clone(),
+            None => format!("#{}", id)
+        }
+    }
+
+    /// Every cycle of at most `max_steps` trades that returns more than it
+    /// costs, most profitable first. Each cycle is reported once, starting
+    /// from its lowest currency id.
+    pub fn profitable_cycles(&self, max_steps: usize) -> Vec<Cycle> {
+        let mut cycles = Vec::<Cycle>::new();
+        let mut starts: Vec<u32> = self.edges.keys().copied().collect();
+        starts.sort();
+
+        for start in starts {
+            let mut path = vec![start];
+            self.walk(start, 1.0, u32::MAX, max_steps, &mut path, &mut cycles);
+        }
+
+        cycles.sort_by(|a, b| b.rate.total_cmp(&a.rate));
+
+        return cycles;
+    }
+
+    fn walk(
+        &self, start: u32, rate: f32, listings: u32, steps_left: usize,
+        path: &mut Vec<u32>, cycles: &mut Vec<Cycle>
+    ) {
+        if steps_left == 0 {
+            return;
+        }
+
+        let current = *path.last().unwrap();
+        for exchange in self.edges.get(&current).into_iter().flatten() {
+            let rate = rate * exchange.rate;
+            let listings = listings.min(exchange.listing_count);
+
+            if exchange.to == start {
+                if path.len() > 1 && rate > 1.0 {
+                    cycles.push(Cycle {
+                        currencies: path.iter().map(|id| self.name(*id)).collect(),
+                        rate,
+                        listing_count: listings
+                    });
+                }
+            } else if exchange.to > start && !path.contains(&exchange.to) {
+                path.push(exchange.to);
+                self.walk(start, rate, listings, steps_left - 1, path, cycles);
+                path.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAOS: u32 = 1;
+    const DIVINE: u32 = 3;
+
+    fn summary(pay: u32, get: u32, value: f32) -> serde_json::Value {
+        serde_json::json!({
+            "id": 0, "league_id": 0, "pay_currency_id": pay, "get_currency_id": get,
+            "sample_time_utc": "2026-01-01T00:00:00Z", "count": 10, "value": value,
+            "data_point_count": 1, "includes_secondary": false, "listing_count": 50
+        })
+    }
+
+    /// Divine Orbs bought for `buy` chaos and sold for `sell` chaos, quoted
+    /// the way poe.ninja does: `receive` in chaos per divine, `pay` in
+    /// divines per chaos.
+    fn divine_market(buy: f32, sell: f32) -> CurrencyData {
+        let spark = serde_json::json!({ "data": [1.0], "totalChange": 0.0 });
+
+        return serde_json::from_value(serde_json::json!({
+            "lines": [{
+                "currencyTypeName": "Divine Orb",
+                "pay": summary(DIVINE, CHAOS, 1.0 / sell),
+                "receive": summary(CHAOS, DIVINE, buy),
+                "paySparkLine": spark, "receiveSparkLine": spark, "chaosEquivalent": buy,
+                "lowConfidencePaySparkLine": spark, "lowConfidenceReceiveSparkLine": spark,
+                "detailsId": "divine-orb"
+            }],
+            "currencyDetails": [
+                { "id": CHAOS, "icon": null, "name": CHAOS_ORB, "tradeId": "chaos" },
+                { "id": DIVINE, "icon": null, "name": "Divine Orb", "tradeId": "divine" }
+            ]
+        })).unwrap();
+    }
+
+    #[test]
+    fn buy_and_sell_prices_are_in_chaos() {
+        let data = divine_market(200.0, 180.0);
+        let line = &data.lines[0];
+
+        assert_eq!(line.buy_price(), Some(200.0));
+        assert_eq!(line.sell_price(), Some(180.0));
+    }
+
+    #[test]
+    fn depth_spread_is_relative_to_the_buy_price() {
+        let depth = Depth::of(&divine_market(200.0, 180.0).lines[0]);
+
+        assert_eq!(depth.buy.as_ref().map(|side| side.chaos), Some(200.0));
+        assert_eq!(depth.sell.as_ref().map(|side| side.chaos), Some(180.0));
+        assert_eq!(depth.spread(), Some(10.0));
+    }
+
+    #[test]
+    fn round_trip_is_unprofitable_when_selling_for_less() {
+        let graph = Graph::from_currency(&divine_market(200.0, 180.0), 0);
+        assert!(graph.profitable_cycles(ROUND_TRIP).is_empty());
+    }
+
+    #[test]
+    fn round_trip_is_profitable_when_selling_for_more() {
+        let graph = Graph::from_currency(&divine_market(180.0, 198.0), 0);
+        let cycles = graph.profitable_cycles(ROUND_TRIP);
+
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].currencies, vec![CHAOS_ORB.to_string(), "Divine Orb".to_string()]);
+        assert!((cycles[0].profit() - 10.0).abs() < 1e-3);
+    }
+}
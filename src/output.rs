@@ -0,0 +1,415 @@
+use std::{
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter,
+    str::FromStr
+};
+
+use serde_json::{ Map, Value };
+
+use crate::convert::Rate;
+use crate::error::Error;
+use crate::poe_ninja::general::NextEnum;
+use crate::poe_ninja::overview::OverviewLine;
+
+pub const DEFAULT_FORMAT: &str = "plain";
+pub const DEFAULT_COLUMNS: &str = "name,chaos,divine,listings";
+
+/// How `exicli` prints price listings. `Plain` is the original
+/// `name: value` output and ignores the column selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Table,
+    Csv,
+    Tsv,
+    Json,
+    Ndjson
+}
+
+#[allow(unused)]
+impl Format {
+    pub fn from(s: &str) -> Option<Format> {
+        match s {
+            "plain" => Some(Format::Plain),
+            "table" => Some(Format::Table),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "json" => Some(Format::Json),
+            "ndjson" => Some(Format::Ndjson),
+            _ => None::<Format>
+        }
+    }
+
+    pub fn show_all() {
+        let mut curr: Option<Self>;
+        let mut i = Self::from(DEFAULT_FORMAT).unwrap();
+        println!("DEFAULT FORMAT: {}\n", i);
+
+        println!("Valid Formats");
+        println!("=============");
+        loop {
+            println!("{}", i);
+            curr = i.next();
+            if curr.is_none() { break; }
+            i = curr.unwrap();
+        }
+    }
+
+    pub fn from_or_default(s: &str) -> (bool, Format) {
+        match Self::from(s) {
+            Some(format) => (true, format),
+            None => (false, Self::from(DEFAULT_FORMAT).unwrap())
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format, Error> {
+        Self::from(s).ok_or_else(|| Error::UnknownType(s.to_string()))
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Format::Plain => write!(f, "plain"),
+            Format::Table => write!(f, "table"),
+            Format::Csv => write!(f, "csv"),
+            Format::Tsv => write!(f, "tsv"),
+            Format::Json => write!(f, "json"),
+            Format::Ndjson => write!(f, "ndjson")
+        }
+    }
+}
+
+impl NextEnum<Format> for Format {
+    fn next(&self) -> Option<Format> {
+        match self {
+            Format::Plain => Some(Format::Table),
+            Format::Table => Some(Format::Csv),
+            Format::Csv => Some(Format::Tsv),
+            Format::Tsv => Some(Format::Json),
+            Format::Json => Some(Format::Ndjson),
+            Format::Ndjson => None::<Format>
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    Chaos,
+    /// The chaos value expressed in the `--unit` currency.
+    Price,
+    Divine,
+    Exalted,
+    Listings,
+    Change,
+    DetailsId,
+    TradeId,
+    Icon,
+    /// The type-specific attributes in one cell, e.g. `level 21, 20%`.
+    Details,
+    GemLevel,
+    Quality,
+    Corrupted,
+    Tier,
+    Links,
+    Variant,
+    ItemType,
+    ItemLevel,
+    LevelRequired
+}
+
+#[allow(unused)]
+impl Column {
+    pub fn from(s: &str) -> Option<Column> {
+        match s {
+            "name" => Some(Column::Name),
+            "chaos" => Some(Column::Chaos),
+            "price" => Some(Column::Price),
+            "divine" => Some(Column::Divine),
+            "exalted" => Some(Column::Exalted),
+            "listings" => Some(Column::Listings),
+            "change" => Some(Column::Change),
+            "details-id" => Some(Column::DetailsId),
+            "trade-id" => Some(Column::TradeId),
+            "icon" => Some(Column::Icon),
+            "details" => Some(Column::Details),
+            "gem-level" => Some(Column::GemLevel),
+            "quality" => Some(Column::Quality),
+            "corrupted" => Some(Column::Corrupted),
+            "tier" => Some(Column::Tier),
+            "links" => Some(Column::Links),
+            "variant" => Some(Column::Variant),
+            "item-type" => Some(Column::ItemType),
+            "item-level" => Some(Column::ItemLevel),
+            "level-required" => Some(Column::LevelRequired),
+            _ => None::<Column>
+        }
+    }
+
+    /// Parses a comma separated list such as `name,chaos,listings`. A column
+    /// named twice is kept once, where it first appears.
+    pub fn parse_list(s: &str) -> Result<Vec<Column>, Error> {
+        let mut columns = Vec::<Column>::new();
+
+        for name in s.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+            let column = Self::from(name).ok_or_else(|| Error::UnknownType(name.to_string()))?;
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        return Ok(columns);
+    }
+
+    pub fn show_all() {
+        let mut curr: Option<Self>;
+        let mut i = Column::Name;
+        println!("DEFAULT COLUMNS: {}\n", DEFAULT_COLUMNS);
+
+        println!("Valid Columns");
+        println!("=============");
+        loop {
+            println!("{}", i);
+            curr = i.next();
+            if curr.is_none() { break; }
+            i = curr.unwrap();
+        }
+    }
+
+    pub fn value<L: OverviewLine>(&self, line: &L, unit: &Rate) -> Value {
+        let attributes = line.attributes();
+
+        match self {
+            Column::Name => Value::from(line.name()),
+            Column::Chaos => number(line.chaos_value()),
+            Column::Price => number(unit.from_chaos(line.chaos_value())),
+            Column::Divine => line.divine_value().map_or(Value::Null, number),
+            Column::Exalted => line.exalted_value().map_or(Value::Null, number),
+            Column::Listings => Value::from(line.listing_count()),
+            Column::Change => number(line.sparkline().totalChange),
+            Column::DetailsId => Value::from(line.details_id()),
+            Column::TradeId => line.trade_id().map_or(Value::Null, Value::from),
+            Column::Icon => line.icon().map_or(Value::Null, Value::from),
+            Column::Details => match attributes.to_string() {
+                details if details.is_empty() => Value::Null,
+                details => Value::from(details)
+            },
+            Column::GemLevel => attributes.gem_level.map_or(Value::Null, Value::from),
+            Column::Quality => attributes.gem_quality.map_or(Value::Null, Value::from),
+            Column::Corrupted => attributes.corrupted.map_or(Value::Null, Value::from),
+            Column::Tier => attributes.map_tier.map_or(Value::Null, Value::from),
+            Column::Links => attributes.links.map_or(Value::Null, Value::from),
+            Column::Variant => attributes.variant.map_or(Value::Null, Value::from),
+            Column::ItemType => attributes.item_type.map_or(Value::Null, Value::from),
+            Column::ItemLevel => attributes.item_level.map_or(Value::Null, Value::from),
+            Column::LevelRequired => attributes.level_required.map_or(Value::Null, Value::from)
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            Column::Name | Column::DetailsId | Column::TradeId | Column::Icon | Column::Details
+                | Column::Corrupted | Column::Variant | Column::ItemType
+        )
+    }
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Column::Name => write!(f, "name"),
+            Column::Chaos => write!(f, "chaos"),
+            Column::Price => write!(f, "price"),
+            Column::Divine => write!(f, "divine"),
+            Column::Exalted => write!(f, "exalted"),
+            Column::Listings => write!(f, "listings"),
+            Column::Change => write!(f, "change"),
+            Column::DetailsId => write!(f, "details-id"),
+            Column::TradeId => write!(f, "trade-id"),
+            Column::Icon => write!(f, "icon"),
+            Column::Details => write!(f, "details"),
+            Column::GemLevel => write!(f, "gem-level"),
+            Column::Quality => write!(f, "quality"),
+            Column::Corrupted => write!(f, "corrupted"),
+            Column::Tier => write!(f, "tier"),
+            Column::Links => write!(f, "links"),
+            Column::Variant => write!(f, "variant"),
+            Column::ItemType => write!(f, "item-type"),
+            Column::ItemLevel => write!(f, "item-level"),
+            Column::LevelRequired => write!(f, "level-required")
+        }
+    }
+}
+
+impl NextEnum<Column> for Column {
+    fn next(&self) -> Option<Column> {
+        match self {
+            Column::Name => Some(Column::Chaos),
+            Column::Chaos => Some(Column::Price),
+            Column::Price => Some(Column::Divine),
+            Column::Divine => Some(Column::Exalted),
+            Column::Exalted => Some(Column::Listings),
+            Column::Listings => Some(Column::Change),
+            Column::Change => Some(Column::DetailsId),
+            Column::DetailsId => Some(Column::TradeId),
+            Column::TradeId => Some(Column::Icon),
+            Column::Icon => Some(Column::Details),
+            Column::Details => Some(Column::GemLevel),
+            Column::GemLevel => Some(Column::Quality),
+            Column::Quality => Some(Column::Corrupted),
+            Column::Corrupted => Some(Column::Tier),
+            Column::Tier => Some(Column::Links),
+            Column::Links => Some(Column::Variant),
+            Column::Variant => Some(Column::ItemType),
+            Column::ItemType => Some(Column::ItemLevel),
+            Column::ItemLevel => Some(Column::LevelRequired),
+            Column::LevelRequired => None::<Column>
+        }
+    }
+}
+
+/// Converts through the shortest decimal form so `0.1f32` is written as
+/// `0.1` rather than `0.10000000149011612`.
+fn number(v: f32) -> Value {
+    v.to_string().parse::<f64>().map_or(Value::Null, Value::from)
+}
+
+/// Text of one cell. Floats are rounded to two places only when `round`
+/// is set, i.e. for the table meant for reading; CSV and TSV keep the full
+/// value like JSON does.
+fn cell(value: &Value, round: bool) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if round && n.is_f64() => format!("{:.2}", f),
+            _ => n.to_string()
+        },
+        other => other.to_string()
+    }
+}
+
+fn escape_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn escape_tsv(s: &str) -> String {
+    s.replace(['\t', '\n', '\r'], " ")
+}
+
+fn render_table(header: &[String], rows: &[Vec<String>], columns: &[Column]) -> String {
+    let widths: Vec<usize> = (0..header.len()).map(|i| {
+        rows.iter()
+            .map(|row| row[i].chars().count())
+            .chain([header[i].chars().count()])
+            .max()
+            .unwrap_or(0)
+    }).collect();
+
+    let format_row = |row: &[String]| -> String {
+        row.iter().enumerate().map(|(i, text)| {
+            if columns[i].is_numeric() {
+                format!("{:>width$}", text, width = widths[i])
+            } else {
+                format!("{:<width$}", text, width = widths[i])
+            }
+        }).collect::<Vec<String>>().join("  ").trim_end().to_string()
+    };
+
+    let mut out = vec![format_row(header)];
+    out.push(widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<String>>().join("  "));
+    out.extend(rows.iter().map(|row| format_row(row)));
+
+    return out.join("\n");
+}
+
+/// Renders `lines` with the chosen columns, pricing the `price` column in
+/// `unit`. `Plain` prints each line's `summary` instead, or its price in
+/// `unit` when that is not chaos.
+pub fn render<L: OverviewLine>(
+    lines: &[L], columns: &[Column], format: &Format, unit: &Rate
+) -> String {
+    // Values stay in column order; only JSON needs them keyed by name.
+    let values: Vec<Vec<Value>> = lines.iter().map(|line| {
+        columns.iter().map(|column| column.value(line, unit)).collect()
+    }).collect();
+    let records = || values.iter().map(|row| {
+        columns.iter().map(|column| column.to_string()).zip(row.iter().cloned())
+            .collect::<Map<String, Value>>()
+    });
+    let header: Vec<String> = columns.iter().map(|column| column.to_string()).collect();
+    let rows: Vec<Vec<String>> = values.iter().map(|row| {
+        row.iter().map(|value| cell(value, *format == Format::Table)).collect()
+    }).collect();
+
+    match format {
+        Format::Plain if unit.chaos != 1.0 => lines.iter()
+            .map(|line| {
+                format!("{}: {:.2} {}", line.label(), unit.from_chaos(line.chaos_value()), unit.name)
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Plain => lines.iter()
+            .map(|line| line.summary())
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Table => render_table(&header, &rows, columns),
+        Format::Csv => [header].iter().chain(rows.iter())
+            .map(|row| row.iter().map(|s| escape_csv(s)).collect::<Vec<String>>().join(","))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Tsv => [header].iter().chain(rows.iter())
+            .map(|row| row.iter().map(|s| escape_tsv(s)).collect::<Vec<String>>().join("\t"))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Format::Json => serde_json::to_string_pretty(&records().collect::<Vec<_>>()).unwrap(),
+        Format::Ndjson => records()
+            .map(|record| serde_json::to_string(&record).unwrap())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poe_ninja::item;
+
+    fn line(name: &str, chaos: f32) -> item::Line {
+        let spark = serde_json::json!({ "data": [], "totalChange": 0.0 });
+
+        return serde_json::from_value(serde_json::json!({
+            "id": 1, "name": name, "icon": "", "baseType": name, "stackSize": null,
+            "artFilename": null, "itemClass": 0, "sparkline": spark,
+            "lowConfidenceSparkline": spark, "implicitModifiers": [], "explicitModifiers": [],
+            "flavourText": "", "chaosValue": chaos, "exaltedValue": 0.0, "divineValue": 0.0,
+            "count": 1, "detailsId": name, "tradeInfo": [], "listingCount": 10
+        })).unwrap();
+    }
+
+    #[test]
+    fn repeated_columns_are_kept_once() {
+        let columns = Column::parse_list("name,chaos,name").unwrap();
+        assert_eq!(columns, vec![Column::Name, Column::Chaos]);
+    }
+
+    #[test]
+    fn rows_follow_the_header_even_with_repeated_columns() {
+        let lines = [line("Gilded Ambush Scarab", 12.5)];
+        let columns = [Column::Name, Column::Chaos, Column::Name];
+
+        let csv = render(&lines, &columns, &Format::Csv, &Rate::chaos());
+        assert_eq!(csv, "name,chaos,name\nGilded Ambush Scarab,12.5,Gilded Ambush Scarab");
+
+        let table = render(&lines, &columns, &Format::Table, &Rate::chaos());
+        assert_eq!(table.lines().count(), 3);
+    }
+}
@@ -1,4 +1,9 @@
 pub mod general;
+pub mod cache;
+pub mod overview;
+pub mod query;
+pub mod search;
+pub mod variant;
 pub mod currency;
 pub mod item;
 pub mod divination;
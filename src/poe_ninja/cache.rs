@@ -0,0 +1,344 @@
+use std::{
+    fs, fs::File, io, io::Write, path::Path, path::PathBuf, process,
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter
+};
+
+use chrono::{ DateTime, Duration, Utc };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::config;
+use crate::error::Error;
+use crate::time;
+
+use super::general::{ get_user_cache_path, Category, League };
+use super::overview::Endpoint;
+
+/// Bumped whenever the layout of cache files changes. Older files are
+/// migrated on load; files with an unknown version are ignored but kept.
+pub const CACHE_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Use the cache while it is fresh, otherwise pull.
+    #[default]
+    Normal,
+    /// Always pull, falling back to the cache only if that fails.
+    Refresh,
+    /// Never pull; any cached data is used regardless of age.
+    Offline
+}
+
+/// How `Overview::load` weighs the cache against poe.ninja.
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    pub mode: CacheMode,
+    pub currency_max_age: Duration,
+    pub item_max_age: Duration,
+    pub divination_max_age: Duration,
+    /// Oldest cache accepted when a pull fails. `None` accepts any age.
+    pub max_stale: Option<Duration>
+}
+
+impl Default for CachePolicy {
+    /// Every category is fresh for the configured cache threshold.
+    fn default() -> CachePolicy {
+        let max_age = Duration::minutes(config::get().cache_threshold());
+
+        CachePolicy {
+            mode: CacheMode::Normal,
+            currency_max_age: max_age,
+            item_max_age: max_age,
+            divination_max_age: max_age,
+            max_stale: None::<Duration>
+        }
+    }
+}
+
+#[allow(unused)]
+impl CachePolicy {
+    pub fn max_age(&self, category: &Category) -> Duration {
+        match category {
+            Category::Currency => self.currency_max_age,
+            Category::Item => self.item_max_age,
+            Category::Divination => self.divination_max_age
+        }
+    }
+
+    /// Sets the same max age for every category.
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.currency_max_age = max_age;
+        self.item_max_age = max_age;
+        self.divination_max_age = max_age;
+    }
+
+    pub fn is_fresh(&self, category: &Category, age: Duration) -> bool {
+        age < self.max_age(category)
+    }
+
+    pub fn accepts_stale(&self, age: Duration) -> bool {
+        match self.max_stale {
+            Some(max_stale) => age <= max_stale,
+            None => true
+        }
+    }
+}
+
+/// Where loaded data came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Cache,
+    Network,
+    /// An out of date cache, used because the pull failed.
+    StaleCache
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Source::Cache => write!(f, "cache"),
+            Source::Network => write!(f, "poe.ninja"),
+            Source::StaleCache => write!(f, "stale cache")
+        }
+    }
+}
+
+/// Describes where the data in a cache file came from.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheHeader {
+    pub schema_version: u32,
+    /// The URL the data was pulled from.
+    pub source: String,
+    pub league: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+    /// When the data was pulled.
+    pub fetched: DateTime<Utc>,
+    /// When poe.ninja last updated the data, from the response headers or
+    /// the trade sample times. Added in version 2.
+    #[serde(default)]
+    pub as_of: Option<DateTime<Utc>>
+}
+
+impl CacheHeader {
+    pub fn new<T: Display>(
+        source: String, league: &League, typ: &T, fetched: DateTime<Utc>
+    ) -> CacheHeader {
+        CacheHeader {
+            schema_version: CACHE_SCHEMA_VERSION,
+            source,
+            league: league.to_string(),
+            typ: typ.to_string(),
+            fetched,
+            as_of: None::<DateTime<Utc>>
+        }
+    }
+
+    /// When the data was current, falling back to the fetch time.
+    pub fn as_of(&self) -> DateTime<Utc> {
+        self.as_of.unwrap_or(self.fetched)
+    }
+
+    /// How old the data itself is, measured from `as_of`.
+    pub fn age(&self) -> Duration {
+        Utc::now() - self.as_of()
+    }
+
+    /// Time since the data was pulled. Freshness is judged by this rather
+    /// than `age`, or a snapshot poe.ninja has not updated for a while would
+    /// be pulled again on every run.
+    pub fn fetch_age(&self) -> Duration {
+        Utc::now() - self.fetched
+    }
+}
+
+/// The on-disk layout of a cached overview.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheFile<T> {
+    pub header: CacheHeader,
+    pub data: T
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into
+/// place, so readers never see a partly written file.
+pub fn write_atomic(path: &str, contents: &str) -> Result<(), Error> {
+    let tmp_path = format!("{}.tmp{}", path, process::id());
+    let written = File::create(&tmp_path).and_then(|mut f| {
+        f.write_all(contents.as_bytes())?;
+        f.sync_all()
+    });
+
+    match written.and_then(|_| fs::rename(&tmp_path, path)) {
+        Ok(()) => return Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(Error::CacheIo(e));
+        }
+    }
+}
+
+/// An overview together with how it was obtained.
+#[derive(Debug, Clone)]
+pub struct Loaded<O> {
+    pub data: O,
+    pub header: CacheHeader,
+    pub source: Source,
+    /// How old the data is, measured from `CacheHeader::as_of`.
+    pub age: Duration,
+    pub policy: CachePolicy
+}
+
+impl<O> Loaded<O> {
+    pub fn new(file: CacheFile<O>, source: Source, policy: &CachePolicy) -> Loaded<O> {
+        Loaded {
+            age: file.header.age(),
+            data: file.data,
+            header: file.header,
+            source,
+            policy: policy.clone()
+        }
+    }
+
+    /// e.g. `cache, 12m old (2024-04-25 10:44:45 +02:00)`.
+    pub fn describe(&self) -> String {
+        let age = if self.age.num_hours() > 0 {
+            format!("{}h {}m", self.age.num_hours(), self.age.num_minutes() % 60)
+        } else {
+            format!("{}m", self.age.num_minutes())
+        };
+
+        format!("{}, {} old ({})", self.source, age, time::display(&self.header.as_of()))
+    }
+}
+
+/// A file in the cache directory.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    /// The league directory the file is in; `None` for `leagues.json`.
+    pub league: Option<String>,
+    pub size: u64,
+    pub modified: DateTime<Utc>
+}
+
+fn entry(path: PathBuf, league: Option<&str>) -> Result<CacheEntry, Error> {
+    let metadata = fs::metadata(&path)?;
+
+    return Ok(CacheEntry {
+        path,
+        league: league.map(|league| league.to_string()),
+        size: metadata.len(),
+        modified: DateTime::<Utc>::from(metadata.modified()?)
+    });
+}
+
+/// Collects only files laid out the way exilian writes them, i.e.
+/// `leagues.json` and `<league>/<endpoint>/<type>.json`, so a cache directory
+/// pointed somewhere shared never yields other programs' files.
+fn collect_entries(dir: &Path, entries: &mut Vec<CacheEntry>) -> Result<(), Error> {
+    let index = dir.join("leagues.json");
+    if index.is_file() {
+        entries.push(entry(index, None)?);
+    }
+
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        if !dir_entry.file_type()?.is_dir() {
+            continue;
+        }
+        let league = dir_entry.file_name().to_string_lossy().to_string();
+
+        for endpoint in [Endpoint::Currency, Endpoint::Item] {
+            let endpoint_dir = dir_entry.path().join(endpoint.cache_dir());
+            if !endpoint_dir.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(&endpoint_dir)? {
+                let path = file?.path();
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                    entries.push(entry(path, Some(&league))?);
+                }
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// Every cached file, sorted by path. An absent cache directory is empty.
+pub fn entries() -> Result<Vec<CacheEntry>, Error> {
+    let dir = PathBuf::from(get_user_cache_path()?);
+    let mut entries = Vec::<CacheEntry>::new();
+
+    match collect_entries(&dir, &mut entries) {
+        Err(Error::CacheIo(e)) if e.kind() == io::ErrorKind::NotFound => {},
+        result => result?
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    return Ok(entries);
+}
+
+/// Total size of the cache in bytes.
+pub fn size() -> Result<u64, Error> {
+    Ok(entries()?.iter().map(|entry| entry.size).sum())
+}
+
+/// Removes the cache of one league, or everything without one. Only files
+/// `entries` lists are deleted, followed by the directories they leave
+/// empty. Returns the number of files removed.
+pub fn clear(league: Option<&League>) -> Result<usize, Error> {
+    let league = league.map(|league| league.to_string());
+    if let Some(name) = &league {
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(Error::UnknownLeague(name.clone()));
+        }
+    }
+
+    let removed: Vec<CacheEntry> = entries()?.into_iter()
+        .filter(|entry| league.is_none() || entry.league == league)
+        .collect();
+    for entry in &removed {
+        fs::remove_file(&entry.path)?;
+    }
+
+    // `remove_dir` fails on directories that still hold anything, which
+    // is what keeps this to the ones just emptied.
+    for entry in removed.iter().filter(|entry| entry.league.is_some()) {
+        if let Some(endpoint_dir) = entry.path.parent() {
+            let _ = fs::remove_dir(endpoint_dir);
+            if let Some(league_dir) = endpoint_dir.parent() {
+                let _ = fs::remove_dir(league_dir);
+            }
+        }
+    }
+    return Ok(removed.len());
+}
+
+/// Removes files not written for longer than `older_than`, returning them.
+pub fn prune(older_than: Duration) -> Result<Vec<CacheEntry>, Error> {
+    let cutoff = Utc::now() - older_than;
+    let mut removed = Vec::<CacheEntry>::new();
+
+    for entry in entries()? {
+        if entry.modified < cutoff {
+            fs::remove_file(&entry.path)?;
+            removed.push(entry);
+        }
+    }
+    return Ok(removed);
+}
+
+/// Parses an age like `30m`, `12h`, `7d` or `2w`. A bare number is minutes.
+pub fn parse_age(s: &str) -> Result<Duration, Error> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let count = s[..split].parse::<i64>().map_err(|_| Error::InvalidAge(s.to_string()))?;
+
+    match &s[split..] {
+        "" | "m" => Ok(Duration::minutes(count)),
+        "h" => Ok(Duration::hours(count)),
+        "d" => Ok(Duration::days(count)),
+        "w" => Ok(Duration::weeks(count)),
+        _ => Err(Error::InvalidAge(s.to_string()))
+    }
+}
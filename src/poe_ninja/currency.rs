@@ -1,36 +1,20 @@
-use core::fmt;
-use std::error::Error;
-
 use std::{
-    fs, fs::File, io::Write, 
     fmt::Display, fmt::Result as FmtResult, fmt::Formatter,
-    path::Path
+    str::FromStr
 };
 
-use chrono::{ DateTime, Local };
+use chrono::{ DateTime, Utc };
 
 use serde::{ Deserialize, Serialize };
 use serde_json;
 
-use fuzzy_matcher::{ FuzzyMatcher, skim::SkimMatcherV2 };
+use crate::error::Error;
+use crate::time;
 
 pub const DEFAULT_TYPE: &str = "Currency";
 
-#[derive(Debug)]
-struct ComError(String);
-
-impl fmt::Display for ComError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ComError: {}", self.0)
-    }
-}
-
-impl Error for ComError {}
-
-use super::general::{ 
-    BASE_URL, CACHE_THRESHOLD, get_user_cache_path, League, TransactionSummary,
-    SparkLine, NextEnum
-};
+use super::general::{ Category, TransactionSummary, SparkLine, NextEnum };
+use super::overview::{ Endpoint, Overview, OverviewLine };
 
 #[allow(non_snake_case, unused)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -43,7 +27,10 @@ pub struct Line {
     pub chaosEquivalent: f32,
     pub lowConfidencePaySparkLine: SparkLine,
     pub lowConfidenceReceiveSparkLine: SparkLine,
-    pub detailsId: String
+    pub detailsId: String,
+    /// Joined from `CurrencyData::currencyDetails` when the data is loaded.
+    #[serde(skip)]
+    pub detail: Option<CurrencyDetail>
 }
 
 #[allow(unused)]
@@ -51,10 +38,76 @@ impl Line {
     pub fn show(&self) {
         println!("{}", &serde_json::to_string(&self).unwrap()); 
     }
+
+    /// poe.ninja's id for this currency, the side of its trades that is not
+    /// chaos.
+    pub fn currency_id(&self) -> Option<u32> {
+        match (&self.pay, &self.receive) {
+            (Some(pay), _) => Some(pay.pay_currency_id),
+            (None, Some(receive)) => Some(receive.get_currency_id),
+            (None, None) => None::<u32>
+        }
+    }
+
+    /// Chaos paid for one unit, from the `receive` side.
+    pub fn buy_price(&self) -> Option<f32> {
+        self.receive.as_ref().map(|receive| receive.value).filter(|v| *v > 0.0)
+    }
+
+    /// Chaos received for one unit. The `pay` side is quoted in units per
+    /// chaos, so it is inverted.
+    pub fn sell_price(&self) -> Option<f32> {
+        self.pay.as_ref().map(|pay| pay.value).filter(|v| *v > 0.0).map(|v| 1.0 / v)
+    }
+}
+
+impl OverviewLine for Line {
+    type Type = CurrencyType;
+    const ENDPOINT: Endpoint = Endpoint::Currency;
+    const CATEGORY: Category = Category::Currency;
+
+    fn name(&self) -> &str {
+        &self.currencyTypeName
+    }
+
+    fn chaos_value(&self) -> f32 {
+        self.chaosEquivalent
+    }
+
+    fn details_id(&self) -> &str {
+        &self.detailsId
+    }
+
+    fn listing_count(&self) -> u32 {
+        match (&self.pay, &self.receive) {
+            (Some(pay), Some(receive)) => pay.listing_count.min(receive.listing_count),
+            (Some(side), None) | (None, Some(side)) => side.listing_count,
+            (None, None) => 0
+        }
+    }
+
+    fn count(&self) -> u32 {
+        let pay = self.pay.as_ref().map_or(0, |pay| pay.count);
+        let receive = self.receive.as_ref().map_or(0, |receive| receive.count);
+
+        return pay + receive;
+    }
+
+    fn sparkline(&self) -> &SparkLine {
+        &self.receiveSparkLine
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.detail.as_ref()?.icon.as_deref()
+    }
+
+    fn trade_id(&self) -> Option<&str> {
+        self.detail.as_ref()?.tradeId.as_deref()
+    }
 }
 
 #[allow(non_snake_case, unused)]
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CurrencyDetail {
     pub id: u32,
     pub icon: Option<String>,
@@ -76,25 +129,18 @@ impl CurrencyType {
             _ => None::<CurrencyType>
         }
     }
-    
-    fn to_string(&self) -> String {
-        match self {
-            CurrencyType::Currency => "Currency".to_string(),
-            CurrencyType::Fragment => "Fragment".to_string()
-        }
-    }
 
     pub fn show_all() {
         let mut curr: Option<Self>;
         let mut i = Self::from("Currency").unwrap();
 
         i = Self::from(DEFAULT_TYPE).unwrap();
-        println!("DEFAULT CURRENCY TYPE: {}\n", i.to_string()); 
+        println!("DEFAULT CURRENCY TYPE: {}\n", i); 
 
         println!("Valid Currency Types");
         println!("====================");
         loop {
-            println!("{}", i.to_string());
+            println!("{}", i);
             curr = i.next();
             if curr.is_none() { break; } 
             i = curr.unwrap(); 
@@ -102,19 +148,27 @@ impl CurrencyType {
     }
     
     pub fn from_or_default(s: &str) -> (bool, CurrencyType) {
-        let type_opt: Option<CurrencyType> = Self::from(s); 
-
-        if type_opt.is_some() {
-            return (true, type_opt.unwrap());
-        } else {
-            return (false, Self::from(&DEFAULT_TYPE).unwrap());
+        match Self::from(s) {
+            Some(typ) => (true, typ),
+            None => (false, Self::from(DEFAULT_TYPE).unwrap())
         }
     }
 }
 
+impl FromStr for CurrencyType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<CurrencyType, Error> {
+        Self::from(s).ok_or_else(|| Error::UnknownType(s.to_string()))
+    }
+}
+
 impl Display for CurrencyType {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        return write!(f, "{}", self.to_string());
+        match self {
+            CurrencyType::Currency => write!(f, "Currency"),
+            CurrencyType::Fragment => write!(f, "Fragment")
+        }
     }
 }
 
@@ -128,11 +182,35 @@ impl NextEnum<CurrencyType> for CurrencyType {
 }
 
 #[allow(non_snake_case, unused)]
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(from = "RawCurrencyData")]
 pub struct CurrencyData {
     pub lines: Vec<Line>,
     pub currencyDetails: Vec<CurrencyDetail>,
-    pub updated: Option<String>
+    pub updated: Option<DateTime<Utc>>
+}
+
+/// `CurrencyData` as poe.ninja sends it, before details are joined on.
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct RawCurrencyData {
+    lines: Vec<Line>,
+    currencyDetails: Vec<CurrencyDetail>,
+    #[serde(default, deserialize_with = "time::deserialize_opt")]
+    updated: Option<DateTime<Utc>>
+}
+
+impl From<RawCurrencyData> for CurrencyData {
+    fn from(raw: RawCurrencyData) -> CurrencyData {
+        let mut data = CurrencyData {
+            lines: raw.lines,
+            currencyDetails: raw.currencyDetails,
+            updated: raw.updated
+        };
+        data.link_details();
+
+        return data;
+    }
 }
 
 #[allow(unused)]
@@ -141,175 +219,56 @@ impl CurrencyData {
         CurrencyData{
             lines: Vec::<Line>::new(),
             currencyDetails: Vec::<CurrencyDetail>::new(),
-            updated: None::<String>
+            updated: None::<DateTime<Utc>>
         }
     }
 
-    fn get_uri(league: &League, typ: &CurrencyType) -> String {
-        format!(
-            "{}currencyoverview?league={}&type={}",
-            BASE_URL,
-            league.to_string(),
-            typ.to_string()
-        )
+    pub fn detail(&self, id: u32) -> Option<&CurrencyDetail> {
+        self.currencyDetails.iter().find(|detail| detail.id == id)
     }
 
-    fn is_current(&self) -> bool {
-        if self.updated.is_none() {
-            return false;
-        }
-
-        let updated = DateTime::parse_from_str(
-            &self.updated.clone().unwrap(),
-            "%Y-%m-%d %H:%M:%S%.9f%:z"
-        ).unwrap();
-        println!("Cached:\t{}\n", updated);
-
-        let now = Local::now().with_timezone(&updated.timezone());
-
-        return (now - updated).num_minutes() < CACHE_THRESHOLD;
-    }
-
-    fn make_cache_path(league: &League) {
-        let base_path = get_user_cache_path();
-        
-        let _ = fs::create_dir_all(format!("{}/{}/currency",
-            base_path,
-            league.to_string(),
-        ));
-    }
-
-    fn get_cache_path(league: &League, typ: &CurrencyType) -> String {
-        format!(
-            "{}/{}/currency/{}.json",
-            get_user_cache_path(),
-            league.to_string(),
-            typ.to_string()
-        )
+    pub fn detail_by_trade_id(&self, trade_id: &str) -> Option<&CurrencyDetail> {
+        self.currencyDetails.iter().find(|detail| {
+            detail.tradeId.as_deref().is_some_and(|id| id.eq_ignore_ascii_case(trade_id))
+        })
     }
 
-    fn cache(json_str: &str, league: &League, typ: &CurrencyType) {
-        Self::make_cache_path(&league);
-        let mut f = File::create(CurrencyData::get_cache_path(&league, &typ)).unwrap();
-        let _ = f.write_all(json_str.as_bytes());
-    }
-    
-    fn load_cache(
-        league: &League, typ: &CurrencyType
-    ) -> Result<CurrencyData, Box<dyn std::error::Error>> {
-
-        let mut contents = fs::read_to_string(CurrencyData::get_cache_path(&league, &typ));
-        let data = serde_json::from_str(&contents.unwrap()).unwrap();
-
-        return Ok(data);
-    }
-
-    pub async fn pull_data(
-        league: &League, typ: &CurrencyType
-    ) -> Result<CurrencyData, Box<dyn std::error::Error>> {
-
-        println!("Pulling from poe.ninja...\n");
-        let resp = reqwest::get(CurrencyData::get_uri(&league, &typ)).await?;
-        if (resp.status() != 200) {
-            let msg = format!("Error communicating with poe.ninja");
-            return Err(Box::new(ComError(msg)));
+    /// Sets `Line::detail` on every line, matching by currency id and falling
+    /// back to the name.
+    pub fn link_details(&mut self) {
+        for line in &mut self.lines {
+            let detail = line.currency_id()
+                .and_then(|id| self.currencyDetails.iter().find(|d| d.id == id))
+                .filter(|detail| detail.name == line.currencyTypeName)
+                .or_else(|| {
+                    self.currencyDetails.iter().find(|d| d.name == line.currencyTypeName)
+                });
+
+            line.detail = detail.cloned();
         }
-        let mut data = resp.json::<CurrencyData>().await?;
-
-        data.updated = Some(Local::now().to_string());
-        CurrencyData::cache(&serde_json::to_string(&data).unwrap(), &league, &typ);
-
-        return Ok(data); 
     }
+}
 
-    pub async fn load(league: &League, typ: &CurrencyType) -> CurrencyData {
-        let mut result: Result<CurrencyData, Box<dyn std::error::Error>>;
-        let mut data = CurrencyData::new();
-        let cache_path = &CurrencyData::get_cache_path(&league, &typ);
-        let path = Path::new(cache_path);
-
-        if path.exists() {
-             result = CurrencyData::load_cache(&league, &typ);
-
-             if result.is_ok() {
-                 data = result.unwrap();
-
-                 if data.is_current() {
-                     return data;
-                 }
-                 println!("Cache is out of date... ");
-             }
-        } 
-
-         result = CurrencyData::pull_data(&league, &typ).await;
-         if result.is_ok() {
-             return result.unwrap();
-         } else {
-            println!("Malformed response from poe.ninja\n");
-
-            if !data.updated.is_none() {
-                let updated = DateTime::parse_from_str(
-                    &data.updated.clone().unwrap(),
-                    "%Y-%m-%d %H:%M:%S%.9f%:z"
-                ).unwrap();
-
-                println!("Using cache from {}\n", updated);
-                return data;
-            }
-         }
-
-         return data;
-    }
+impl Overview for CurrencyData {
+    type Line = Line;
 
-    pub fn update(&mut self, league: League, typ: CurrencyType) {
-        async {
-            let result = CurrencyData::pull_data(&league, &typ).await;
-            
-            if result.is_ok() {
-                let data = result.unwrap();
-                self.lines = data.lines;
-                self.currencyDetails = data.currencyDetails;
-                self.updated = Some(Local::now().to_string())
-            }
-            todo!("Do something if request results in error.")
-        };
+    fn lines(&self) -> &[Line] {
+        &self.lines
     }
-    
-    pub fn find(&self, s: &str) -> Option<Line> {
-        for line in &self.lines {
-            if line.currencyTypeName == s {
-                return Some(line.clone());
-            }
-        }
-        return None::<Line>;
-    }
-    
-    pub fn ffind(&self, s: &str) -> Vec<Line> {
-        let mut results = Vec::<Line>::new(); 
-        let matcher = SkimMatcherV2::default();
-
-        for line in &self.lines {
-            if matcher.fuzzy_match(&line.currencyTypeName, s).is_some() {
-                results.push(line.clone());
-            }
-        }
 
-        return results;
-    }
-    
-    pub fn show(&self) {
-        println!("{}", &serde_json::to_string(&self).unwrap()); 
+    fn updated(&self) -> Option<DateTime<Utc>> {
+        self.updated
     }
 
-    pub fn show_prices(&self, s: &str, raw: bool) {
-        let lines = self.ffind(s);
+    fn set_updated(&mut self, updated: DateTime<Utc>) {
+        self.updated = Some(updated);
+    }
 
-        if raw {
-            println!("{}", &serde_json::to_string(&self.lines).unwrap());
-        } else {
-            for line in &lines {
-                println!("{}: {}c", line.currencyTypeName, line.chaosEquivalent);
-            }
-        } 
+    fn sampled(&self) -> Option<DateTime<Utc>> {
+        self.lines.iter()
+            .flat_map(|line| [&line.pay, &line.receive])
+            .flatten()
+            .map(|summary| summary.sample_time_utc)
+            .max()
     }
 }
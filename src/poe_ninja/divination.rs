@@ -1,15 +1,40 @@
-use serde::Deserialize;
-use super::general::{ SparkLine, Modifier, TradeInfo };
+use std:: {
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter,
+    str::FromStr
+};
+
+use serde_json;
+use serde::{ Deserialize, Serialize };
+
+use crate::error::Error;
+
+use super::general::{ Category, SparkLine, Modifier, TradeInfo, NextEnum };
+use super::overview::{ Endpoint, OverviewData, OverviewLine };
+
+pub const DEFAULT_TYPE: &str = "DivinationCard";
+
+/// Tags poe.ninja wraps the reward of a card in, e.g.
+/// `<currencyitem>{3x Exalted Orb}`.
+const REWARD_TAGS: [(&str, RewardKind); 8] = [
+    ("uniqueitem", RewardKind::Unique),
+    ("currencyitem", RewardKind::Currency),
+    ("gemitem", RewardKind::Gem),
+    ("divination", RewardKind::Divination),
+    ("rareitem", RewardKind::Item),
+    ("magicitem", RewardKind::Item),
+    ("normalitem", RewardKind::Item),
+    ("whiteitem", RewardKind::Item)
+];
 
 #[allow(non_snake_case, unused)]
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Line {
     pub id: u32,
     pub name: String,
     pub icon: String,
     pub baseType: String,
     pub stackSize: Option<u32>,
-    pub artFilename: String,
+    pub artFilename: Option<String>,
     pub itemClass: u32,
     pub sparkline: SparkLine,
     pub lowConfidenceSparkline: SparkLine,
@@ -25,8 +50,261 @@ pub struct Line {
     pub listingCount: u32,
 }
 
-#[allow(non_snake_case, unused)]
-#[derive(Deserialize)]
-pub struct DivinationData {
-    pub lines: Vec<Line>
+#[allow(unused)]
+impl Line {
+    pub fn show(&self) {
+        println!("{}", &serde_json::to_string(&self).unwrap());
+    }
+
+    pub fn stack_size(&self) -> u32 {
+        self.stackSize.unwrap_or(1)
+    }
+
+    /// Chaos value of a full stack, i.e. what it costs to buy one reward.
+    pub fn set_value(&self) -> f32 {
+        self.stack_size() as f32 * self.chaosValue
+    }
+
+    pub fn reward(&self) -> Option<Reward> {
+        let corrupted = self.explicitModifiers.iter().any(|m| {
+            m.text.contains("<corrupted>")
+        });
+
+        for modifier in &self.explicitModifiers {
+            if let Some(mut reward) = Reward::parse(&modifier.text) {
+                reward.corrupted = corrupted;
+                return Some(reward);
+            }
+        }
+        return None::<Reward>;
+    }
+}
+
+impl OverviewLine for Line {
+    type Type = DivinationType;
+    const ENDPOINT: Endpoint = Endpoint::Item;
+    const CATEGORY: Category = Category::Divination;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn chaos_value(&self) -> f32 {
+        self.chaosValue
+    }
+
+    fn details_id(&self) -> &str {
+        &self.detailsId
+    }
+
+    fn listing_count(&self) -> u32 {
+        self.listingCount
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+
+    fn sparkline(&self) -> &SparkLine {
+        &self.sparkline
+    }
+
+    fn divine_value(&self) -> Option<f32> {
+        Some(self.divineValue)
+    }
+
+    fn exalted_value(&self) -> Option<f32> {
+        Some(self.exaltedValue)
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some(&self.icon)
+    }
+
+    fn search_fields(&self) -> Vec<&str> {
+        let mut fields = vec![self.baseType.as_str(), self.flavourText.as_str()];
+        fields.extend(self.implicitModifiers.iter().map(|m| m.text.as_str()));
+        fields.extend(self.explicitModifiers.iter().map(|m| m.text.as_str()));
+
+        return fields;
+    }
+
+    fn summary(&self) -> String {
+        let reward = match self.reward() {
+            Some(reward) => reward.to_string(),
+            None => "?".to_string()
+        };
+
+        format!(
+            "{}: {}c x{} = {}c -> {}",
+            self.name,
+            self.chaosValue,
+            self.stack_size(),
+            self.set_value(),
+            reward
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewardKind {
+    Unique,
+    Currency,
+    Gem,
+    Divination,
+    Item
+}
+
+/// What a full set of a card is exchanged for.
+#[derive(Debug, Clone)]
+pub struct Reward {
+    pub kind: RewardKind,
+    pub name: String,
+    pub quantity: u32,
+    pub corrupted: bool
+}
+
+#[allow(unused)]
+impl Reward {
+    /// Parses one modifier text, returning `None` when it does not contain a
+    /// reward tag (flavour lines like `<default>{Item Level:}`).
+    pub fn parse(text: &str) -> Option<Reward> {
+        for (tag, kind) in REWARD_TAGS {
+            let open = format!("<{}>{{", tag);
+
+            if let Some(start) = text.find(&open) {
+                let inner = Self::braced(&text[start + open.len()..]);
+                let inner = Self::strip_tags(inner);
+                let (quantity, name) = Self::split_quantity(inner.trim());
+
+                return Some(Reward {
+                    kind,
+                    name: name.to_string(),
+                    quantity,
+                    corrupted: false
+                });
+            }
+        }
+        return None::<Reward>;
+    }
+
+    /// Returns `s` up to the brace closing the one just before it.
+    fn braced(s: &str) -> &str {
+        let mut depth = 1;
+
+        for (i, c) in s.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return &s[..i];
+                    }
+                },
+                _ => {}
+            }
+        }
+        return s;
+    }
+
+    fn strip_tags(s: &str) -> String {
+        let mut result = String::new();
+        let mut in_tag = false;
+
+        for c in s.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                '{' | '}' => {},
+                _ if !in_tag => result.push(c),
+                _ => {}
+            }
+        }
+        return result;
+    }
+
+    /// Splits a leading `3x ` off `s`.
+    fn split_quantity(s: &str) -> (u32, &str) {
+        if let Some((count, rest)) = s.split_once("x ") {
+            if let Ok(count) = count.parse::<u32>() {
+                return (count, rest.trim());
+            }
+        }
+        return (1, s);
+    }
+}
+
+impl Display for Reward {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.quantity > 1 {
+            write!(f, "{}x ", self.quantity)?;
+        }
+        write!(f, "{}", self.name)?;
+        if self.corrupted {
+            write!(f, " (corrupted)")?;
+        }
+        return Ok(());
+    }
+}
+
+pub enum DivinationType {
+    DivinationCard
+}
+
+#[allow(unused)]
+impl DivinationType {
+    pub fn from(s: &str) -> Option<DivinationType> {
+        match s {
+            "DivinationCard" => Some(DivinationType::DivinationCard),
+            _ => None::<DivinationType>
+        }
+    }
+
+    pub fn show_all() {
+        let mut curr: Option<Self>;
+        let mut i = Self::from(DEFAULT_TYPE).unwrap();
+        println!("DEFAULT DIVINATION TYPE: {}\n", i);
+
+        println!("Valid Divination Types");
+        println!("======================");
+        loop {
+            println!("{}", i);
+            curr = i.next();
+            if curr.is_none() { break; }
+            i = curr.unwrap();
+        }
+    }
+
+    pub fn from_or_default(s: &str) -> (bool, DivinationType) {
+        match Self::from(s) {
+            Some(typ) => (true, typ),
+            None => (false, Self::from(DEFAULT_TYPE).unwrap())
+        }
+    }
+}
+
+impl FromStr for DivinationType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DivinationType, Error> {
+        Self::from(s).ok_or_else(|| Error::UnknownType(s.to_string()))
+    }
+}
+
+impl Display for DivinationType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            DivinationType::DivinationCard => write!(f, "DivinationCard")
+        }
+    }
 }
+
+impl NextEnum<DivinationType> for DivinationType {
+    fn next(&self) -> Option<DivinationType> {
+        match self {
+            DivinationType::DivinationCard => None::<DivinationType>
+        }
+    }
+}
+
+pub type DivinationData = OverviewData<Line>;
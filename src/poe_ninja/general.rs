@@ -1,19 +1,149 @@
-use std:: { fmt::Display, fmt::Result as FmtResult, fmt::Formatter };
+use std::{
+    env, fs, io, sync::RwLock,
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter,
+    str::FromStr
+};
+
+use chrono::{ DateTime, Duration, Utc };
+
+use reqwest::header::{ HeaderMap, HeaderName, AGE, DATE, LAST_MODIFIED };
 
 use homedir::get_my_home;
 
 use serde::{ Deserialize, Serialize };
+use serde_json;
+
+use crate::config;
+use crate::error::Error;
+use crate::time;
+
+use super::cache::{ write_atomic, CacheMode, CachePolicy };
 
 pub const CACHE_THRESHOLD: i64 = 15;
+pub const LEAGUE_CACHE_THRESHOLD: i64 = 24 * 60;
 pub const BASE_URL: &str = "https://poe.ninja/api/data/";
 pub const DEFAULT_CATEGORY: &str = "Currency";
 pub const DEFAULT_LEAGUE: &str = "Necropolis";
 
-pub fn get_user_cache_path() -> String {
-    format!(
-        "{}/.cache/exilian",
-        get_my_home().unwrap().unwrap().to_string_lossy().to_string()
-    )
+static CACHE_DIR: RwLock<Option<String>> = RwLock::new(None);
+
+/// Overrides the cache directory for the rest of the process, taking
+/// precedence over the config file and environment.
+pub fn set_cache_dir(dir: &str) {
+    if let Ok(mut current) = CACHE_DIR.write() {
+        *current = Some(dir.to_string());
+    }
+}
+
+/// The cache directory: the one given to `set_cache_dir`, else `cache_dir`
+/// from the config (or `EXILIAN_CACHE_DIR`), else `$XDG_CACHE_HOME/exilian`,
+/// else `$HOME/.cache/exilian`.
+pub fn get_user_cache_path() -> Result<String, Error> {
+    if let Some(dir) = cache_dir_override() {
+        return Ok(dir);
+    }
+    if let Ok(dir) = env::var("XDG_CACHE_HOME") {
+        if !dir.is_empty() {
+            return Ok(format!("{}/exilian", dir));
+        }
+    }
+
+    match get_my_home() {
+        Ok(Some(home)) => Ok(format!("{}/.cache/exilian", home.to_string_lossy())),
+        _ => Err(Error::CacheIo(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not determine the cache directory; set XDG_CACHE_HOME or EXILIAN_CACHE_DIR"
+        )))
+    }
+}
+
+/// A cache directory set with `set_cache_dir` or in the config.
+fn cache_dir_override() -> Option<String> {
+    if let Ok(current) = CACHE_DIR.read() {
+        if let Some(dir) = current.as_ref() {
+            return Some(dir.clone());
+        }
+    }
+    return config::get().cache_dir.clone();
+}
+
+/// Where price history is kept: `$XDG_DATA_HOME/exilian`, else the cache
+/// directory when it has been overridden, else `$HOME/.local/share/exilian`.
+pub fn get_user_data_path() -> Result<String, Error> {
+    if let Ok(dir) = env::var("XDG_DATA_HOME") {
+        if !dir.is_empty() {
+            return Ok(format!("{}/exilian", dir));
+        }
+    }
+    if let Some(dir) = cache_dir_override() {
+        return Ok(dir);
+    }
+
+    match get_my_home() {
+        Ok(Some(home)) => Ok(format!("{}/.local/share/exilian", home.to_string_lossy())),
+        _ => Err(Error::CacheIo(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not determine the data directory; set XDG_DATA_HOME or EXILIAN_CACHE_DIR"
+        )))
+    }
+}
+
+/// HTTP client with the configured user agent and proxy.
+fn client() -> Result<reqwest::Client, Error> {
+    let config = config::get();
+    let mut builder = reqwest::Client::builder().user_agent(config.user_agent());
+
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    return Ok(builder.build()?);
+}
+
+/// A decoded response with what its headers say about its freshness.
+pub struct Fetched<T> {
+    pub body: T,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub date: Option<DateTime<Utc>>,
+    /// Time the response spent in caches on the way, from `Age`.
+    pub age: Option<Duration>
+}
+
+impl<T> Fetched<T> {
+    /// When the data was current: `Last-Modified` if sent, otherwise the
+    /// response `Date` (or now) minus its `Age`.
+    pub fn as_of(&self) -> Option<DateTime<Utc>> {
+        if self.last_modified.is_some() {
+            return self.last_modified;
+        }
+        return self.age.map(|age| self.date.unwrap_or_else(Utc::now) - age);
+    }
+}
+
+fn header_time(headers: &HeaderMap, name: HeaderName) -> Option<DateTime<Utc>> {
+    let value = headers.get(name)?.to_str().ok()?;
+
+    return DateTime::parse_from_rfc2822(value).ok().map(|time| time.with_timezone(&Utc));
+}
+
+/// Fetches `uri` and decodes the JSON body, keeping network failures, bad
+/// status codes and malformed bodies apart.
+pub async fn fetch_json<T: serde::de::DeserializeOwned>(uri: &str) -> Result<Fetched<T>, Error> {
+    let resp = client()?.get(uri).send().await?;
+    if !resp.status().is_success() {
+        return Err(Error::Status(resp.status().as_u16()));
+    }
+    let headers = resp.headers().clone();
+    let body = resp.text().await?;
+
+    return Ok(Fetched {
+        body: serde_json::from_str(&body).map_err(Error::Decode)?,
+        last_modified: header_time(&headers, LAST_MODIFIED),
+        date: header_time(&headers, DATE),
+        age: headers.get(AGE)
+            .and_then(|age| age.to_str().ok()?.parse::<i64>().ok())
+            .map(Duration::seconds)
+    });
 }
 
 pub trait NextEnum<T> {
@@ -21,10 +151,11 @@ pub trait NextEnum<T> {
 }
 
 #[allow(unused)]
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Category {
     Currency,
-    Item
+    Item,
+    Divination
 }
 
 #[allow(unused)]
@@ -33,28 +164,20 @@ impl Category {
         match s {
             "Currency" => Some(Category::Currency),
             "Item" => Some(Category::Item),
+            "Divination" => Some(Category::Divination),
             _ => None::<Category>
         }
     }
- 
-    pub fn to_string(&self) -> String {
-        match self {
-            Category::Currency => "Currency".to_string(),
-            Category::Item => "Item".to_string()
-        }
-    }
     
     pub fn show_all() {
         let mut curr: Option<Self>;
-        let mut i = Self::from("Currency").unwrap();
-        
-        i = Self::from(DEFAULT_CATEGORY).unwrap();
-        println!("DEFAULT CATEGORY: {}\n", i.to_string()); 
+        let mut i = Self::from(DEFAULT_CATEGORY).unwrap();
+        println!("DEFAULT CATEGORY: {}\n", i); 
         
         println!("Valid Categories");
         println!("================");
         loop {
-            println!("{}", i.to_string());
+            println!("{}", i);
             curr = i.next();
             if curr.is_none() { break; } 
             i = curr.unwrap(); 
@@ -62,12 +185,27 @@ impl Category {
     }
     
     pub fn from_or_default(s: &str) -> (bool, Category) {
-        let cat_opt: Option<Category> = Self::from(s); 
+        match Self::from(s) {
+            Some(cat) => (true, cat),
+            None => (false, Self::from(DEFAULT_CATEGORY).unwrap())
+        }
+    }
+}
 
-        if cat_opt.is_some() {
-            return (true, cat_opt.unwrap());
-        } else {
-            return (false, Self::from(&DEFAULT_CATEGORY).unwrap());
+impl FromStr for Category {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Category, Error> {
+        Self::from(s).ok_or_else(|| Error::UnknownType(s.to_string()))
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Category::Currency => write!(f, "Currency"),
+            Category::Item => write!(f, "Item"),
+            Category::Divination => write!(f, "Divination")
         }
     }
 }
@@ -76,11 +214,13 @@ impl NextEnum<Category> for Category {
     fn next(&self) -> Option<Category>{
         match self {
             Category::Currency => Some(Category::Item),
-            Category::Item => None::<Category> 
+            Category::Item => Some(Category::Divination),
+            Category::Divination => None::<Category>
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum League {
     Standard,
     Hardcore,
@@ -95,21 +235,40 @@ pub enum League {
     Affliction,
     AfflictionHC,
     AfflictionRuthless,
-    AfflictionHCRuthless 
+    AfflictionHCRuthless,
+
+    Other(String)
 }
 
 #[allow(unused)]
 impl League {
-    pub fn from_or_default(s: &str) -> (bool, League) {
-        let league_opt: Option<League> = Self::from(s); 
+    /// The league named `s`, spelled as the index lists it when it does.
+    /// Names the index does not know, e.g. because it could not be loaded,
+    /// are kept as given; only an empty `s` falls back to `current_league`.
+    pub fn from_or_default(s: &str, index: &LeagueIndex) -> (bool, League) {
+        if s.is_empty() {
+            return (false, index.current_league());
+        }
 
-        if league_opt.is_some() {
-            return (true, league_opt.unwrap());
-        } else {
-            return (false, Self::from(&DEFAULT_LEAGUE).unwrap());
+        match Self::from_index(s, index) {
+            Ok(league) => (true, league),
+            Err(_) => (true, Self::from_name(s))
         }
     }
 
+    /// Resolves `s` against the discovered leagues and the known variants,
+    /// using poe.ninja's spelling of the name so each league has one cache.
+    pub fn from_index(s: &str, index: &LeagueIndex) -> Result<League, Error> {
+        if let Some(matched) = index.find(s).filter(|_| !s.is_empty()) {
+            return Ok(Self::from_name(&matched.name));
+        }
+        if let Some(league) = Self::from(s) {
+            return Ok(league);
+        }
+
+        return Err(Error::UnknownLeague(s.to_string()));
+    }
+
     pub fn from(s: &str) -> Option<League> {
         match s {
             "Standard" => Some(League::Standard),
@@ -130,41 +289,36 @@ impl League {
             _ => None::<League>
         }
     }
-    
-    pub fn to_string(&self) -> String {
-        match self {
-            League::Standard => "Standard".to_string(),
-            League::Hardcore => "Hardcore".to_string(),
-            League::Ruthless => "Ruthless".to_string(),
-            League::HCRuthless => "Hardcore+Ruthless".to_string(),
-            
-            League::Necropolis => "Necropolis".to_string(),
-            League::NecropolisHC => "Hardcore+Necropolis".to_string(),
-            League::NecropolisRuthless => "Ruthless+Necropolis".to_string(),
-            League::NecropolisHCRuthless => "HC+Ruthless+Necropolis".to_string(),
 
-            League::Affliction => "Affliction".to_string(),
-            League::AfflictionHC => "Hardcore+Affliction".to_string(),
-            League::AfflictionRuthless => "Ruthless+Affliction".to_string(),
-            League::AfflictionHCRuthless => "HC+Ruthless+Affliction".to_string(),
+    /// Like `from`, but keeps unknown names as `League::Other` instead of
+    /// rejecting them.
+    pub fn from_name(s: &str) -> League {
+        let normalized = s.replace(' ', "+");
+
+        match Self::from(&normalized) {
+            Some(league) => league,
+            None => League::Other(s.to_string())
         }
     }
     
-    pub fn show_all() {
-        let mut curr: Option<Self>;
-        let mut i = Self::from("Standard").unwrap();
-        
-        i = Self::from(DEFAULT_LEAGUE).unwrap();
-        println!("DEFAULT LEAGUE: {}\n", i.to_string()); 
-        
+    pub fn show_all(index: &LeagueIndex) {
+        println!("DEFAULT LEAGUE: {}\n", index.current_league()); 
 
         println!("Valid Leagues");
         println!("=============");
-        loop {
-            println!("{}", i.to_string());
-            curr = i.next();
-            if curr.is_none() { break; } 
-            i = curr.unwrap(); 
+        if index.economyLeagues.is_empty() {
+            let mut curr: Option<Self>;
+            let mut i = League::Standard;
+            loop {
+                println!("{}", i);
+                curr = i.next();
+                if curr.is_none() { break; } 
+                i = curr.unwrap(); 
+            }
+        } else {
+            for league in &index.economyLeagues {
+                println!("{}", league.name);
+            }
         }
     }
 }
@@ -185,44 +339,194 @@ impl NextEnum<League> for League {
             League::Affliction => Some(League::AfflictionHC),
             League::AfflictionHC => Some(League::AfflictionRuthless),
             League::AfflictionRuthless => Some(League::AfflictionHCRuthless),
-            League::AfflictionHCRuthless => None::<League>
+            League::AfflictionHCRuthless => None::<League>,
+
+            League::Other(_) => None::<League>
         }
     }
 }
 
 impl Display for League {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        return write!(f, "{}", self.to_string());
+        match self {
+            League::Standard => write!(f, "Standard"),
+            League::Hardcore => write!(f, "Hardcore"),
+            League::Ruthless => write!(f, "Ruthless"),
+            League::HCRuthless => write!(f, "Hardcore+Ruthless"),
+            
+            League::Necropolis => write!(f, "Necropolis"),
+            League::NecropolisHC => write!(f, "Hardcore+Necropolis"),
+            League::NecropolisRuthless => write!(f, "Ruthless+Necropolis"),
+            League::NecropolisHCRuthless => write!(f, "HC+Ruthless+Necropolis"),
+
+            League::Affliction => write!(f, "Affliction"),
+            League::AfflictionHC => write!(f, "Hardcore+Affliction"),
+            League::AfflictionRuthless => write!(f, "Ruthless+Affliction"),
+            League::AfflictionHCRuthless => write!(f, "HC+Ruthless+Affliction"),
+
+            League::Other(name) => write!(f, "{}", name)
+        }
+    }
+}
+
+#[allow(non_snake_case, unused)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EconomyLeague {
+    pub name: String,
+    pub url: String,
+    pub displayName: String,
+    #[serde(default)]
+    pub hardcore: bool,
+    #[serde(default)]
+    pub indexed: bool
+}
+
+#[allow(unused)]
+impl EconomyLeague {
+    /// A challenge league is a temporary softcore trade league, i.e. not
+    /// Standard and not one of its Hardcore/Ruthless/SSF offshoots.
+    pub fn is_challenge(&self) -> bool {
+        let name = self.name.to_lowercase();
+
+        return !self.hardcore
+            && name != "standard"
+            && !name.contains("hardcore")
+            && !name.contains("ruthless")
+            && !name.contains("ssf");
+    }
+}
+
+#[allow(non_snake_case, unused)]
+#[derive(Default, Deserialize, Serialize)]
+pub struct LeagueIndex {
+    pub economyLeagues: Vec<EconomyLeague>,
+    #[serde(default)]
+    pub oldEconomyLeagues: Vec<EconomyLeague>,
+    /// When the index was fetched, which decides when it is pulled again.
+    #[serde(default, deserialize_with = "time::deserialize_opt")]
+    pub updated: Option<DateTime<Utc>>
+}
+
+#[allow(unused)]
+impl LeagueIndex {
+    pub fn new() -> LeagueIndex {
+        LeagueIndex{
+            economyLeagues: Vec::<EconomyLeague>::new(),
+            oldEconomyLeagues: Vec::<EconomyLeague>::new(),
+            updated: None::<DateTime<Utc>>
+        }
+    }
+
+    fn get_uri() -> String {
+        format!("{}getindexstate", config::get().base_url())
+    }
+
+    fn is_current(&self) -> bool {
+        match self.updated {
+            Some(updated) => (Utc::now() - updated).num_minutes() < LEAGUE_CACHE_THRESHOLD,
+            None => false
+        }
+    }
+
+    fn get_cache_path() -> Result<String, Error> {
+        Ok(format!("{}/leagues.json", get_user_cache_path()?))
+    }
+
+    fn cache(json_str: &str) -> Result<(), Error> {
+        fs::create_dir_all(get_user_cache_path()?)?;
+
+        return write_atomic(&LeagueIndex::get_cache_path()?, json_str);
+    }
+
+    fn load_cache() -> Result<LeagueIndex, Error> {
+        let contents = fs::read_to_string(LeagueIndex::get_cache_path()?)?;
+
+        return serde_json::from_str(&contents).map_err(Error::CacheCorrupt);
+    }
+
+    pub async fn pull_data() -> Result<LeagueIndex, Error> {
+        let mut data = fetch_json::<LeagueIndex>(&LeagueIndex::get_uri()).await?.body;
+
+        data.updated = Some(Utc::now());
+        LeagueIndex::cache(&serde_json::to_string(&data).unwrap())?;
+
+        return Ok(data);
+    }
+
+    /// Loads the league list from cache, refreshing it from poe.ninja once a
+    /// day, or always with `CacheMode::Refresh` and never offline. Falls back
+    /// to a stale cache, then to an empty index, so callers can always use
+    /// `current_league` and get at least `DEFAULT_LEAGUE`.
+    pub async fn load(policy: &CachePolicy) -> LeagueIndex {
+        let mut data = LeagueIndex::new();
+
+        if let Ok(cached) = LeagueIndex::load_cache() {
+            data = cached;
+
+            if policy.mode == CacheMode::Normal && data.is_current() {
+                return data;
+            }
+        }
+        if policy.mode == CacheMode::Offline {
+            return data;
+        }
+
+        match LeagueIndex::pull_data().await {
+            Ok(fresh) => fresh,
+            Err(_) => data
+        }
+    }
+
+    pub fn contains(&self, s: &str) -> bool {
+        self.find(s).is_some()
+    }
+
+    /// The listed league named `s`, treating `+` and spaces alike.
+    pub fn find(&self, s: &str) -> Option<&EconomyLeague> {
+        let normalized = s.replace('+', " ");
+
+        return self.economyLeagues.iter().find(|league| {
+            league.name.replace('+', " ") == normalized
+        });
+    }
+
+    /// The first challenge league poe.ninja lists, or `DEFAULT_LEAGUE` when
+    /// the index could not be loaded.
+    pub fn current_league(&self) -> League {
+        match self.economyLeagues.iter().find(|league| league.is_challenge()) {
+            Some(league) => League::from_name(&league.name),
+            None => League::from_name(DEFAULT_LEAGUE)
+        }
     }
 }
 
 #[allow(unused)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TransactionSummary {
-    id: u32,
-    league_id: u32,
-    pay_currency_id: u32,
-    get_currency_id: u32,
-    sample_time_utc: String,
-    count: u32,
-    value: f32,
-    data_point_count: u32,
-    includes_secondary: bool,
-    listing_count: u32
+    pub id: u32,
+    pub league_id: u32,
+    pub pay_currency_id: u32,
+    pub get_currency_id: u32,
+    pub sample_time_utc: DateTime<Utc>,
+    pub count: u32,
+    pub value: f32,
+    pub data_point_count: u32,
+    pub includes_secondary: bool,
+    pub listing_count: u32
 }
 
 #[allow(non_snake_case, unused)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SparkLine {
-    data: Vec<Option<f32>>,
-    totalChange: f32
+    pub data: Vec<Option<f32>>,
+    pub totalChange: f32
 }
 
 #[allow(non_snake_case, unused)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Modifier {
-    text: String,
-    optional: bool
+    pub text: String,
+    pub optional: bool
 }
 
 #[allow(non_snake_case, unused)]
@@ -1,36 +1,19 @@
-use core::fmt;
-use std::error::Error;
-
 use std:: { 
-    fs, fs::File, io::Write,
     fmt::Display, fmt::Result as FmtResult, fmt::Formatter,
-    path::Path
+    str::FromStr
 };
 
-use chrono::{ DateTime, Local };
+use chrono::{ DateTime, Utc };
+
+use crate::error::Error;
 
 use serde_json;
 use serde::{ Deserialize, Serialize };
-use super::general::{ 
-    BASE_URL, CACHE_THRESHOLD, get_user_cache_path, League, SparkLine, Modifier,
-    TradeInfo, NextEnum
-};
-use fuzzy_matcher::FuzzyMatcher;
-use fuzzy_matcher::skim::SkimMatcherV2;
-
-#[derive(Debug)]
-struct ComError(String);
-
-impl fmt::Display for ComError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ComError: {}", self.0)
-    }
-}
+use super::general::{ Category, SparkLine, Modifier, TradeInfo, NextEnum };
+use super::overview::{ Attributes, Endpoint, OverviewData, OverviewLine };
 
 pub const DEFAULT_TYPE: &str = "Tattoo";
 
-impl Error for ComError {}
-
 #[allow(non_snake_case, unused)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Line {
@@ -53,6 +36,25 @@ pub struct Line {
     pub detailsId: String,
     pub tradeInfo: Vec<TradeInfo>,
     pub listingCount: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gemLevel: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gemQuality: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrupted: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapTier: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub levelRequired: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub itemType: Option<String>,
+    /// Only set for cluster jewels and base types.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub itemLevel: Option<u32>
 }
 
 #[allow(unused)]
@@ -62,15 +64,76 @@ impl Line {
     }
 }
 
+impl OverviewLine for Line {
+    type Type = ItemType;
+    const ENDPOINT: Endpoint = Endpoint::Item;
+    const CATEGORY: Category = Category::Item;
 
-#[allow(non_snake_case, unused)]
-#[derive(Deserialize, Serialize, Clone)]
-pub struct ItemData {
-    pub lines: Vec<Line>,
-    pub updated: Option<String>
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn chaos_value(&self) -> f32 {
+        self.chaosValue
+    }
+
+    fn details_id(&self) -> &str {
+        &self.detailsId
+    }
+
+    fn listing_count(&self) -> u32 {
+        self.listingCount
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+
+    fn sparkline(&self) -> &SparkLine {
+        &self.sparkline
+    }
+
+    fn divine_value(&self) -> Option<f32> {
+        Some(self.divineValue)
+    }
+
+    fn exalted_value(&self) -> Option<f32> {
+        Some(self.exaltedValue)
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some(&self.icon)
+    }
+
+    fn attributes(&self) -> Attributes {
+        Attributes {
+            gem_level: self.gemLevel,
+            gem_quality: self.gemQuality,
+            corrupted: self.corrupted,
+            map_tier: self.mapTier,
+            level_required: self.levelRequired,
+            links: self.links,
+            variant: self.variant.clone(),
+            item_type: self.itemType.clone(),
+            item_level: self.itemLevel
+        }
+    }
+
+    fn search_fields(&self) -> Vec<&str> {
+        let mut fields = vec![self.baseType.as_str(), self.flavourText.as_str()];
+        fields.extend(self.implicitModifiers.iter().map(|m| m.text.as_str()));
+        fields.extend(self.explicitModifiers.iter().map(|m| m.text.as_str()));
+
+        return fields;
+    }
 }
 
+pub type ItemData = OverviewData<Line>;
+
+/// The `type=` values of poe.ninja's `itemoverview`. `Other` carries any
+/// type string not listed here yet.
 #[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ItemType {
     Tattoo,
     Omen,
@@ -83,6 +146,7 @@ pub enum ItemType {
     UniqueAccessory,
     UniqueFlask,
     UniqueJewel,
+    UniqueTincture,
     UniqueRelic,
     SkillGem,
     ClusterJewel,
@@ -98,9 +162,19 @@ pub enum ItemType {
     BaseType,
     Fossil,
     Resonator,
+    HelmetEnchant,
     Beast,
     Essence,
-    Vial
+    Vial,
+    Coffin,
+    AllflameEmber,
+    KalguuranRune,
+    Runegraft,
+    Wombgift,
+    Blueprint,
+    Contract,
+    IncursionTemple,
+    Other(String)
 }
 
 #[allow(unused)]
@@ -118,6 +192,7 @@ impl ItemType {
             "UniqueAccessory" => Some(ItemType::UniqueAccessory),
             "UniqueFlask" => Some(ItemType::UniqueFlask),
             "UniqueJewel" => Some(ItemType::UniqueJewel),
+            "UniqueTincture" => Some(ItemType::UniqueTincture),
             "UniqueRelic" => Some(ItemType::UniqueRelic),
             "SkillGem" => Some(ItemType::SkillGem),
             "ClusterJewel" => Some(ItemType::ClusterJewel),
@@ -133,44 +208,28 @@ impl ItemType {
             "BaseType" => Some(ItemType::BaseType),
             "Fossil" => Some(ItemType::Fossil),
             "Resonator" => Some(ItemType::Resonator),
+            "HelmetEnchant" => Some(ItemType::HelmetEnchant),
             "Beast" => Some(ItemType::Beast),
             "Essence" => Some(ItemType::Essence),
             "Vial" => Some(ItemType::Vial),
+            "Coffin" => Some(ItemType::Coffin),
+            "AllflameEmber" => Some(ItemType::AllflameEmber),
+            "KalguuranRune" => Some(ItemType::KalguuranRune),
+            "Runegraft" => Some(ItemType::Runegraft),
+            "Wombgift" => Some(ItemType::Wombgift),
+            "Blueprint" => Some(ItemType::Blueprint),
+            "Contract" => Some(ItemType::Contract),
+            "IncursionTemple" => Some(ItemType::IncursionTemple),
             _ => None::<ItemType>
         }
     }
 
-    pub fn to_string(&self) -> String {
-        match self {
-            ItemType::Tattoo => "Tattoo".to_string(),
-            ItemType::Omen => "Omen".to_string(),
-            ItemType::DivinationCard => "DivinationCard".to_string(),
-            ItemType::Artifact => "Artifact".to_string(),
-            ItemType::Oil => "Oil".to_string(),
-            ItemType::Incubator => "Incubator".to_string(),
-            ItemType::UniqueWeapon => "UniqueWeapon".to_string(),
-            ItemType::UniqueArmour => "UniqueArmour".to_string(),
-            ItemType::UniqueAccessory => "UniqueAccessory".to_string(),
-            ItemType::UniqueFlask => "UniqueFlask".to_string(),
-            ItemType::UniqueJewel => "UnqiueJewel".to_string(),
-            ItemType::UniqueRelic => "UniqueRelic".to_string(),
-            ItemType::SkillGem => "SkillGem".to_string(),
-            ItemType::ClusterJewel => "ClusterJewel".to_string(),
-            ItemType::Map => "Map".to_string(),
-            ItemType::BlightedMap => "BlightedMap".to_string(),
-            ItemType::BlightRavagedMap => "BlightRavagedMap".to_string(),
-            ItemType::ScourgedMap => "ScourgedMap".to_string(),
-            ItemType::UniqueMap => "UniqueMap".to_string(),
-            ItemType::DeliriumOrb => "DeliriumOrb".to_string(),
-            ItemType::Invitation => "Invitation".to_string(),
-            ItemType::Scarab => "Scarab".to_string(),
-            ItemType::Memory => "Memory".to_string(),
-            ItemType::BaseType => "BaseType".to_string(),
-            ItemType::Fossil => "Fossil".to_string(),
-            ItemType::Resonator => "Resonator".to_string(),
-            ItemType::Beast => "Beast".to_string(),
-            ItemType::Essence => "Essence".to_string(),
-            ItemType::Vial => "Vial".to_string()
+    /// Like `from`, but keeps an unknown string as `Other` so a type poe.ninja
+    /// added since can still be fetched.
+    pub fn from_raw(s: &str) -> ItemType {
+        match Self::from(s) {
+            Some(typ) => typ,
+            None => ItemType::Other(s.to_string())
         }
     }
     
@@ -179,12 +238,12 @@ impl ItemType {
         let mut i = Self::from("Tattoo").unwrap();
 
         i = Self::from(DEFAULT_TYPE).unwrap();
-        println!("DEFAULT ITEM TYPE: {}\n", i.to_string()); 
+        println!("DEFAULT ITEM TYPE: {}\n", i); 
 
         println!("Valid Item Types");
         println!("================");
         loop {
-            println!("{}", i.to_string());
+            println!("{}", i);
             curr = i.next();
             if curr.is_none() { break; } 
             i = curr.unwrap(); 
@@ -192,12 +251,9 @@ impl ItemType {
     }
     
     pub fn from_or_default(s: &str) -> (bool, ItemType) {
-        let type_opt: Option<ItemType> = Self::from(s); 
-
-        if type_opt.is_some() {
-            return (true, type_opt.unwrap());
-        } else {
-            return (false, Self::from(&DEFAULT_TYPE).unwrap());
+        match Self::from(s) {
+            Some(typ) => (true, typ),
+            None => (false, Self::from(DEFAULT_TYPE).unwrap())
         }
     }
 }
@@ -215,7 +271,8 @@ impl NextEnum<ItemType> for ItemType {
             ItemType::UniqueArmour => Some(ItemType::UniqueAccessory),
             ItemType::UniqueAccessory => Some(ItemType::UniqueFlask),
             ItemType::UniqueFlask => Some(ItemType::UniqueJewel),
-            ItemType::UniqueJewel => Some(ItemType::UniqueRelic),
+            ItemType::UniqueJewel => Some(ItemType::UniqueTincture),
+            ItemType::UniqueTincture => Some(ItemType::UniqueRelic),
             ItemType::UniqueRelic => Some(ItemType::SkillGem),
             ItemType::SkillGem => Some(ItemType::ClusterJewel),
             ItemType::ClusterJewel => Some(ItemType::Map),
@@ -230,17 +287,76 @@ impl NextEnum<ItemType> for ItemType {
             ItemType::Memory => Some(ItemType::BaseType),
             ItemType::BaseType => Some(ItemType::Fossil),
             ItemType::Fossil => Some(ItemType::Resonator),
-            ItemType::Resonator => Some(ItemType::Beast),
+            ItemType::Resonator => Some(ItemType::HelmetEnchant),
+            ItemType::HelmetEnchant => Some(ItemType::Beast),
             ItemType::Beast => Some(ItemType::Essence),
             ItemType::Essence => Some(ItemType::Vial),
-            ItemType::Vial => None::<ItemType>
+            ItemType::Vial => Some(ItemType::Coffin),
+            ItemType::Coffin => Some(ItemType::AllflameEmber),
+            ItemType::AllflameEmber => Some(ItemType::KalguuranRune),
+            ItemType::KalguuranRune => Some(ItemType::Runegraft),
+            ItemType::Runegraft => Some(ItemType::Wombgift),
+            ItemType::Wombgift => Some(ItemType::Blueprint),
+            ItemType::Blueprint => Some(ItemType::Contract),
+            ItemType::Contract => Some(ItemType::IncursionTemple),
+            ItemType::IncursionTemple => None::<ItemType>,
+            ItemType::Other(_) => None::<ItemType>
         }
     }
-}    
+}
+
+impl FromStr for ItemType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ItemType, Error> {
+        Self::from(s).ok_or_else(|| Error::UnknownType(s.to_string()))
+    }
+}
 
 impl Display for ItemType {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        return write!(f, "{}", self.to_string());
+        match self {
+            ItemType::Tattoo => write!(f, "Tattoo"),
+            ItemType::Omen => write!(f, "Omen"),
+            ItemType::DivinationCard => write!(f, "DivinationCard"),
+            ItemType::Artifact => write!(f, "Artifact"),
+            ItemType::Oil => write!(f, "Oil"),
+            ItemType::Incubator => write!(f, "Incubator"),
+            ItemType::UniqueWeapon => write!(f, "UniqueWeapon"),
+            ItemType::UniqueArmour => write!(f, "UniqueArmour"),
+            ItemType::UniqueAccessory => write!(f, "UniqueAccessory"),
+            ItemType::UniqueFlask => write!(f, "UniqueFlask"),
+            ItemType::UniqueJewel => write!(f, "UniqueJewel"),
+            ItemType::UniqueTincture => write!(f, "UniqueTincture"),
+            ItemType::UniqueRelic => write!(f, "UniqueRelic"),
+            ItemType::SkillGem => write!(f, "SkillGem"),
+            ItemType::ClusterJewel => write!(f, "ClusterJewel"),
+            ItemType::Map => write!(f, "Map"),
+            ItemType::BlightedMap => write!(f, "BlightedMap"),
+            ItemType::BlightRavagedMap => write!(f, "BlightRavagedMap"),
+            ItemType::ScourgedMap => write!(f, "ScourgedMap"),
+            ItemType::UniqueMap => write!(f, "UniqueMap"),
+            ItemType::DeliriumOrb => write!(f, "DeliriumOrb"),
+            ItemType::Invitation => write!(f, "Invitation"),
+            ItemType::Scarab => write!(f, "Scarab"),
+            ItemType::Memory => write!(f, "Memory"),
+            ItemType::BaseType => write!(f, "BaseType"),
+            ItemType::Fossil => write!(f, "Fossil"),
+            ItemType::Resonator => write!(f, "Resonator"),
+            ItemType::HelmetEnchant => write!(f, "HelmetEnchant"),
+            ItemType::Beast => write!(f, "Beast"),
+            ItemType::Essence => write!(f, "Essence"),
+            ItemType::Vial => write!(f, "Vial"),
+            ItemType::Coffin => write!(f, "Coffin"),
+            ItemType::AllflameEmber => write!(f, "AllflameEmber"),
+            ItemType::KalguuranRune => write!(f, "KalguuranRune"),
+            ItemType::Runegraft => write!(f, "Runegraft"),
+            ItemType::Wombgift => write!(f, "Wombgift"),
+            ItemType::Blueprint => write!(f, "Blueprint"),
+            ItemType::Contract => write!(f, "Contract"),
+            ItemType::IncursionTemple => write!(f, "IncursionTemple"),
+            ItemType::Other(s) => write!(f, "{}", s)
+        }
     }
 }
 
@@ -249,179 +365,58 @@ impl ItemData {
     pub fn new() -> ItemData {
         ItemData{
             lines: Vec::<Line>::new(),
-            updated: None::<String>
-        }
-    }
-
-    fn get_uri(league: &League, typ: &ItemType) -> String {
-        format!(
-            "{}itemoverview?league={}&type={}",
-            BASE_URL,
-            league.to_string(),
-            typ.to_string()
-        )
-    }
-    
-    fn is_current(&self) -> bool {
-        if self.updated.is_none() {
-            return false;
+            updated: None::<DateTime<Utc>>
         }
-
-        let updated = DateTime::parse_from_str(
-            &self.updated.clone().unwrap(),
-            "%Y-%m-%d %H:%M:%S%.9f%:z"
-        ).unwrap();
-        println!("Cached:\t{}\n", updated);
-
-        let now = Local::now().with_timezone(&updated.timezone());
-
-        return (now - updated).num_minutes() < CACHE_THRESHOLD;
-    }
-    
-    fn make_cache_path(league: &League) {
-        let base_path = get_user_cache_path();
- 
-        let _ = fs::create_dir_all(format!("{}/{}/item", 
-            base_path,
-            league.to_string(),
-        ));
-    }
-
-    fn get_cache_path(league: &League, typ: &ItemType) -> String {
-        format!(
-            "{}/{}/item/{}.json",
-            get_user_cache_path(),
-            league.to_string(),
-            typ.to_string()
-        )
-    }
-
-    fn cache(json_str: &str, league: &League, typ: &ItemType) {
-        Self::make_cache_path(&league);
-        let mut f = File::create(
-            ItemData::get_cache_path(&league, &typ)
-        ).unwrap();
-        let _ = f.write_all(json_str.as_bytes());
     }
+}
 
-    fn load_cache(
-        league: &League, typ: &ItemType
-    ) -> Result<ItemData, Box<dyn std::error::Error>> {
-
-        let mut contents = fs::read_to_string(
-            ItemData::get_cache_path(&league, &typ)
-        );
-        let data = serde_json::from_str(&contents.unwrap()).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        return Ok(data);
+    fn all_types() -> Vec<ItemType> {
+        let mut types = vec![ItemType::from(DEFAULT_TYPE).unwrap()];
+        while let Some(next) = types.last().unwrap().next() {
+            types.push(next);
+        }
+        return types;
     }
 
-    pub async fn pull_data(
-        league: &League, typ: &ItemType
-    ) -> Result<ItemData, Box<dyn std::error::Error>> {
-
-        println!("Pulling from poe.ninja...\n");
-        let resp = reqwest::get(ItemData::get_uri(&league, &typ)).await?;
-        if (resp.status() != 200) {
-            let msg = format!("Error communicating with poe.ninja");
-            return Err(Box::new(ComError(msg)));
+    #[test]
+    fn every_type_round_trips_through_its_api_string() {
+        for typ in all_types() {
+            let api = typ.to_string();
+            assert_eq!(ItemType::from(&api), Some(typ.clone()), "{}", api);
+            assert_eq!(api.parse::<ItemType>().unwrap(), typ);
         }
-        let mut data = resp.json::<ItemData>().await?;
-
-        data.updated = Some(Local::now().to_string());
-        ItemData::cache(&serde_json::to_string(&data).unwrap(), &league, &typ);
-
-        return Ok(data); 
-    }
-    
-    pub async fn load(league: &League, typ: &ItemType) -> ItemData {
-        let mut result: Result<ItemData, Box<dyn std::error::Error>>;
-        let mut data = ItemData::new();
-        let cache_path = &ItemData::get_cache_path(&league, &typ);
-        let path = Path::new(cache_path);
-
-        if  path.exists() {
-             result = ItemData::load_cache(&league, &typ);
-
-             if result.is_ok() {
-                 data = result.unwrap();
-
-                 if data.is_current() {
-                     return data;
-                 }
-
-                 println!("Cache is out of date... ");
-             }
-        } 
-
-         result = ItemData::pull_data(&league, &typ).await;
-         if result.is_ok() {
-             return result.unwrap();
-         } else { 
-            println!("Malformed response from poe.ninja\n");
-            
-            if !data.updated.is_none() {
-                let updated = DateTime::parse_from_str(
-                    &data.updated.clone().unwrap(),
-                    "%Y-%m-%d %H:%M:%S%.9f%:z"
-                ).unwrap();
-
-                println!("Using cache from {}\n", updated);
-                return data;
-            }
-         }
-
-         return data;
     }
 
-    pub fn update(&mut self, league: League, typ: ItemType) {
-        async {
-            let result = ItemData::pull_data(&league, &typ).await;
-            
-            if result.is_ok() {
-                let data = result.unwrap();
-                self.lines = data.lines;
-            }
-
-            todo!("Do something if request results in error.")
-        };
-    }
+    #[test]
+    fn api_strings_are_unique() {
+        let mut names: Vec<String> = all_types().iter().map(|typ| typ.to_string()).collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
 
-    pub fn find(&self, s: &str) -> Option<Line> {
-        for line in &self.lines {
-            if line.name == s {
-                return Some(line.clone());
-            }
-        }
-        return None::<Line>;
+        assert_eq!(names.len(), count);
     }
-    
-    pub fn ffind(&self, s: &str) -> Vec<Line> {
-        let mut results = Vec::<Line>::new(); 
-        let matcher = SkimMatcherV2::default();
-
-        for line in &self.lines {
-            if matcher.fuzzy_match(&line.name, s).is_some() {
-                results.push(line.clone());
-            }
-        }
 
-        return results;
+    #[test]
+    fn catalog_lists_every_variant() {
+        assert_eq!(all_types().len(), 39);
     }
-    
-    pub fn show(&self) {
-        println!("{}", &serde_json::to_string(&self).unwrap()); 
+
+    #[test]
+    fn unique_jewel_uses_the_api_spelling() {
+        assert_eq!(ItemType::UniqueJewel.to_string(), "UniqueJewel");
+        assert_eq!(ItemType::from("UniqueJewel"), Some(ItemType::UniqueJewel));
     }
 
-    pub fn show_prices(&self, s: &str, raw: bool) {
-        let lines = self.ffind(s);
-        
-        if raw {
-            println!("{}", &serde_json::to_string(&self.lines).unwrap());
-        } else {
-            for line in &lines {
-                println!("{}: {}c", line.name, line.chaosValue);
-            } 
-        }
+    #[test]
+    fn raw_types_fall_back_to_other() {
+        assert_eq!(ItemType::from_raw("Scarab"), ItemType::Scarab);
+        assert_eq!(ItemType::from_raw("DjinnCoin"), ItemType::Other("DjinnCoin".to_string()));
+        assert_eq!(ItemType::from_raw("DjinnCoin").to_string(), "DjinnCoin");
+        assert!("DjinnCoin".parse::<ItemType>().is_err());
     }
 }
@@ -0,0 +1,584 @@
+use std::{
+    fs, io,
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter, collections::HashMap
+};
+
+use async_trait::async_trait;
+
+use chrono::{ DateTime, Utc };
+
+use serde::{ Deserialize, Serialize, de::DeserializeOwned };
+use serde_json::{ self, Value };
+
+use crate::config;
+use crate::convert::Rate;
+use crate::error::Error;
+use crate::history::History;
+use crate::log::{ emit, Event };
+use crate::output::{ render, Column, Format };
+use crate::time;
+
+use super::general::{
+    Category, fetch_json, get_user_cache_path, League, SparkLine
+};
+use super::cache::{
+    write_atomic, CacheFile, CacheHeader, CacheMode, CachePolicy, Loaded, Source,
+    CACHE_SCHEMA_VERSION
+};
+use super::query::Query;
+use super::search::{ search, Fields, MatchMode };
+use super::variant::{ Selector, VariantGroup };
+
+/// The poe.ninja API an overview is served from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Currency,
+    Item
+}
+
+impl Endpoint {
+    pub fn path(&self) -> &'static str {
+        match self {
+            Endpoint::Currency => "currencyoverview",
+            Endpoint::Item => "itemoverview"
+        }
+    }
+
+    pub fn cache_dir(&self) -> &'static str {
+        match self {
+            Endpoint::Currency => "currency",
+            Endpoint::Item => "item"
+        }
+    }
+}
+
+/// Type-specific properties that tell apart lines sharing a name, such as
+/// gem level or map tier. Absent ones are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub gem_level: Option<u32>,
+    pub gem_quality: Option<u32>,
+    pub corrupted: Option<bool>,
+    pub map_tier: Option<u32>,
+    pub level_required: Option<u32>,
+    pub links: Option<u32>,
+    pub variant: Option<String>,
+    pub item_type: Option<String>,
+    pub item_level: Option<u32>
+}
+
+impl Attributes {
+    pub fn is_empty(&self) -> bool {
+        *self == Attributes::default()
+    }
+}
+
+/// e.g. `level 21, 20%, corrupted` or `6L, Relic`. `item_type` and
+/// `level_required` are left out as they rarely tell lines apart.
+impl Display for Attributes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let mut parts = Vec::<String>::new();
+
+        if let Some(level) = self.gem_level {
+            parts.push(format!("level {}", level));
+        }
+        if let Some(quality) = self.gem_quality {
+            parts.push(format!("{}%", quality));
+        }
+        if let Some(tier) = self.map_tier {
+            parts.push(format!("T{}", tier));
+        }
+        if let Some(links) = self.links {
+            parts.push(format!("{}L", links));
+        }
+        if let Some(item_level) = self.item_level {
+            parts.push(format!("ilvl {}", item_level));
+        }
+        if let Some(variant) = &self.variant {
+            parts.push(variant.clone());
+        }
+        if self.corrupted == Some(true) {
+            parts.push("corrupted".to_string());
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// A single priced entry of an overview.
+pub trait OverviewLine: Clone + Serialize + DeserializeOwned + Send + Sync {
+    /// The `type=` values accepted by `ENDPOINT`.
+    type Type: Display + Sync;
+    const ENDPOINT: Endpoint;
+    const CATEGORY: Category;
+
+    fn name(&self) -> &str;
+    fn chaos_value(&self) -> f32;
+    fn listing_count(&self) -> u32;
+    fn details_id(&self) -> &str;
+    /// Number of trades the price is based on.
+    fn count(&self) -> u32;
+    /// poe.ninja's 7 day trend, in percent change.
+    fn sparkline(&self) -> &SparkLine;
+
+    fn divine_value(&self) -> Option<f32> {
+        None::<f32>
+    }
+
+    fn exalted_value(&self) -> Option<f32> {
+        None::<f32>
+    }
+
+    fn icon(&self) -> Option<&str> {
+        None::<&str>
+    }
+
+    /// Id used by the official trade site, searched like the name.
+    fn trade_id(&self) -> Option<&str> {
+        None::<&str>
+    }
+
+    fn attributes(&self) -> Attributes {
+        Attributes::default()
+    }
+
+    /// The name, followed by the attributes when any are shown.
+    fn label(&self) -> String {
+        let attributes = self.attributes().to_string();
+
+        if attributes.is_empty() {
+            self.name().to_string()
+        } else {
+            format!("{} ({})", self.name(), attributes)
+        }
+    }
+
+    /// Text besides the name that `Fields::All` searches.
+    fn search_fields(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// One line of `show_prices` output.
+    fn summary(&self) -> String {
+        format!("{}: {}c", self.label(), self.chaos_value())
+    }
+}
+
+pub type TypeOf<O> = <<O as Overview>::Line as OverviewLine>::Type;
+
+/// Loading, caching, search and output shared by every overview. Implementors
+/// only provide access to their lines and fetch timestamp.
+#[async_trait]
+pub trait Overview: Default + Serialize + DeserializeOwned + Send + Sync {
+    type Line: OverviewLine;
+
+    fn lines(&self) -> &[Self::Line];
+    /// When the data was current, as recorded at fetch time.
+    fn updated(&self) -> Option<DateTime<Utc>>;
+    fn set_updated(&mut self, updated: DateTime<Utc>);
+
+    /// Latest trade sample time in the data, if the overview has any.
+    fn sampled(&self) -> Option<DateTime<Utc>> {
+        None::<DateTime<Utc>>
+    }
+
+    fn get_uri(league: &League, typ: &TypeOf<Self>) -> String {
+        format!(
+            "{}{}?league={}&type={}",
+            config::get().base_url(),
+            Self::Line::ENDPOINT.path(),
+            league,
+            typ
+        )
+    }
+
+    fn make_cache_path(league: &League) -> Result<(), Error> {
+        let base_path = get_user_cache_path()?;
+
+        fs::create_dir_all(format!("{}/{}/{}",
+            base_path,
+            league,
+            Self::Line::ENDPOINT.cache_dir()
+        ))?;
+
+        return Ok(());
+    }
+
+    fn get_cache_path(league: &League, typ: &TypeOf<Self>) -> Result<String, Error> {
+        Ok(format!(
+            "{}/{}/{}/{}.json",
+            get_user_cache_path()?,
+            league,
+            Self::Line::ENDPOINT.cache_dir(),
+            typ
+        ))
+    }
+
+    fn cache(&self, header: &CacheHeader, league: &League, typ: &TypeOf<Self>) -> Result<(), Error> {
+        Self::make_cache_path(league)?;
+        let file = CacheFile { header: header.clone(), data: self };
+        let json_str = serde_json::to_string(&file).map_err(Error::CacheCorrupt)?;
+
+        return write_atomic(&Self::get_cache_path(league, typ)?, &json_str);
+    }
+
+    /// Reads the cache file. Files from before the header was introduced and
+    /// version 1 files are migrated in place; files with any other schema
+    /// version, such as ones a newer build wrote, are left alone and reported
+    /// as `CacheVersion`.
+    fn load_cache(league: &League, typ: &TypeOf<Self>) -> Result<CacheFile<Self>, Error> {
+        let path = Self::get_cache_path(league, typ)?;
+        let contents = fs::read_to_string(&path)?;
+        let value: Value = serde_json::from_str(&contents).map_err(Error::CacheCorrupt)?;
+        let version = value.get("header")
+            .and_then(|header| header.get("schema_version"))
+            .and_then(Value::as_u64);
+
+        match version {
+            Some(version) if version == CACHE_SCHEMA_VERSION as u64 => {
+                return serde_json::from_value(value).map_err(Error::CacheCorrupt);
+            },
+            Some(1) | None => {
+                let migrated = match version {
+                    Some(_) => serde_json::from_value::<CacheFile<Self>>(value).map(|mut file| {
+                        file.header.schema_version = CACHE_SCHEMA_VERSION;
+                        file
+                    }),
+                    None => serde_json::from_value::<Self>(value).map(|data| {
+                        let fetched = data.updated().unwrap_or(DateTime::<Utc>::MIN_UTC);
+                        let uri = Self::get_uri(league, typ);
+                        CacheFile { header: CacheHeader::new(uri, league, typ, fetched), data }
+                    })
+                };
+                let file = migrated.map_err(Error::CacheCorrupt)?;
+
+                file.data.cache(&file.header, league, typ)?;
+                emit(Event::CacheMigrated { path: &path });
+
+                return Ok(file);
+            },
+            Some(version) => return Err(Error::CacheVersion(version))
+        }
+    }
+
+    async fn pull_data(league: &League, typ: &TypeOf<Self>) -> Result<CacheFile<Self>, Error> {
+        let uri = Self::get_uri(league, typ);
+        emit(Event::Pulling { uri: &uri });
+        let fetched = fetch_json::<Self>(&uri).await?;
+        let as_of = fetched.as_of();
+        let mut data = fetched.body;
+        let mut header = CacheHeader::new(uri, league, typ, Utc::now());
+
+        header.as_of = as_of.or_else(|| data.sampled());
+        data.set_updated(header.as_of());
+        data.cache(&header, league, typ)?;
+
+        // The history is a side record; the pull itself still succeeded.
+        let recorded = History::open::<Self::Line>(league, typ)
+            .and_then(|history| history.record(data.lines()));
+        if let Err(e) = recorded {
+            emit(Event::HistoryUnwritable { error: &e });
+        }
+
+        return Ok(CacheFile { header, data });
+    }
+
+    /// Loads the overview as `policy` directs. Normally the cache is used
+    /// while fresh and otherwise a pull is made; a stale cache is still used
+    /// when the pull fails and `policy` accepts its age, otherwise the pull
+    /// error is passed on.
+    async fn load(
+        league: &League, typ: &TypeOf<Self>, policy: &CachePolicy
+    ) -> Result<Loaded<Self>, Error> {
+        let cached = match Self::load_cache(league, typ) {
+            Ok(cached) => Some(cached),
+            Err(Error::CacheIo(e)) if e.kind() == io::ErrorKind::NotFound => None::<CacheFile<Self>>,
+            Err(e) => {
+                emit(Event::CacheUnreadable { error: &e });
+                None::<CacheFile<Self>>
+            }
+        };
+
+        match (policy.mode, cached) {
+            (CacheMode::Offline, Some(cached)) => {
+                return Ok(Loaded::new(cached, Source::Cache, policy));
+            },
+            (CacheMode::Offline, None) => return Err(Error::NotCached),
+            (CacheMode::Normal, Some(cached))
+                if policy.is_fresh(&Self::Line::CATEGORY, cached.header.fetch_age()) => {
+                return Ok(Loaded::new(cached, Source::Cache, policy));
+            },
+            (CacheMode::Normal, Some(cached)) => {
+                emit(Event::CacheOutOfDate);
+                return Self::pull_or_stale(league, typ, Some(cached), policy).await;
+            },
+            (_, cached) => return Self::pull_or_stale(league, typ, cached, policy).await
+        }
+    }
+
+    async fn pull_or_stale(
+        league: &League, typ: &TypeOf<Self>, cached: Option<CacheFile<Self>>,
+        policy: &CachePolicy
+    ) -> Result<Loaded<Self>, Error> {
+        let e = match Self::pull_data(league, typ).await {
+            Ok(fresh) => return Ok(Loaded::new(fresh, Source::Network, policy)),
+            Err(e) => e
+        };
+
+        match cached {
+            Some(cached) if policy.accepts_stale(cached.header.age()) => {
+                emit(Event::UsingStaleCache {
+                    error: &e,
+                    updated: cached.header.as_of()
+                });
+                return Ok(Loaded::new(cached, Source::StaleCache, policy));
+            },
+            _ => return Err(e)
+        }
+    }
+
+    /// Pulls a fresh overview into `self` (rewriting the cache) and reports
+    /// how it differs from what was loaded before.
+    async fn update(
+        &mut self, league: &League, typ: &TypeOf<Self>
+    ) -> Result<UpdateSummary, Error> {
+        let fresh = Self::pull_data(league, typ).await?.data;
+        let summary = UpdateSummary::between(self.lines(), fresh.lines());
+        *self = fresh;
+
+        return Ok(summary);
+    }
+
+    /// The first line named `s`. Items like gems have one line per variant;
+    /// `variants` returns all of them.
+    fn find(&self, s: &str) -> Option<Self::Line> {
+        for line in self.lines() {
+            if line.name() == s {
+                return Some(line.clone());
+            }
+        }
+        return None::<Self::Line>;
+    }
+
+    /// Every line named `name` that `selector` accepts.
+    fn variants(&self, name: &str, selector: &Selector) -> Vec<Self::Line> {
+        self.lines().iter()
+            .filter(|line| line.name() == name && selector.matches(&line.attributes()))
+            .cloned()
+            .collect()
+    }
+
+    /// Lines whose name fuzzy matches `s`, best match first.
+    fn ffind(&self, s: &str) -> Vec<Self::Line> {
+        self.search(s, &MatchMode::Fuzzy, Fields::Name)
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect()
+    }
+
+    /// Lines matching `pattern` paired with their score, best first.
+    fn search(&self, pattern: &str, mode: &MatchMode, fields: Fields) -> Vec<(i64, Self::Line)> {
+        search(self.lines(), pattern, mode, fields)
+    }
+
+    /// Lines matching `query.search`, filtered, sorted and paged.
+    fn query(&self, query: &Query) -> Vec<Self::Line> {
+        let found = self.search(&query.search, &query.mode, query.fields)
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+
+        return query.apply(found);
+    }
+
+    fn show(&self) {
+        println!("{}", &serde_json::to_string(&self).unwrap());
+    }
+
+    fn show_prices(&self, query: &Query, format: &Format, columns: &[Column], unit: &Rate) {
+        let lines = self.query(query);
+
+        if !lines.is_empty() {
+            println!("{}", render(&lines, columns, format, unit));
+        }
+    }
+
+    /// Prints the lines `query` selects grouped by name, each group with the
+    /// price range across its variants.
+    fn show_variants(&self, query: &Query, unit: &Rate) {
+        let groups = VariantGroup::group(self.query(query));
+
+        for group in &groups {
+            println!("{}", group.render(unit));
+        }
+    }
+
+    /// Prints the full serialized lines matching `s`.
+    fn show_raw(&self, s: &str) {
+        println!("{}", &serde_json::to_string(&self.ffind(s)).unwrap());
+    }
+}
+
+/// A chaos value that moved between two pulls.
+#[derive(Debug, Clone)]
+pub struct PriceDelta {
+    pub details_id: String,
+    pub old: f32,
+    pub new: f32
+}
+
+impl PriceDelta {
+    pub fn change(&self) -> f32 {
+        self.new - self.old
+    }
+}
+
+/// What an `update` changed, keyed by `detailsId`.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<PriceDelta>
+}
+
+impl UpdateSummary {
+    pub fn between<L: OverviewLine>(old: &[L], new: &[L]) -> UpdateSummary {
+        let mut summary = UpdateSummary::default();
+        let old_values: HashMap<&str, f32> = old.iter()
+            .map(|line| (line.details_id(), line.chaos_value()))
+            .collect();
+        let new_values: HashMap<&str, f32> = new.iter()
+            .map(|line| (line.details_id(), line.chaos_value()))
+            .collect();
+
+        for line in new {
+            match old_values.get(line.details_id()) {
+                Some(&old) if old != line.chaos_value() => {
+                    summary.changed.push(PriceDelta {
+                        details_id: line.details_id().to_string(),
+                        old,
+                        new: line.chaos_value()
+                    });
+                },
+                Some(_) => {},
+                None => summary.added.push(line.details_id().to_string())
+            }
+        }
+
+        for line in old {
+            if !new_values.contains_key(line.details_id()) {
+                summary.removed.push(line.details_id().to_string());
+            }
+        }
+
+        return summary;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An overview that carries nothing but its lines, which is the shape of
+/// every `itemoverview` response.
+#[allow(non_snake_case, unused)]
+#[derive(Deserialize, Serialize, Clone)]
+pub struct OverviewData<L> {
+    pub lines: Vec<L>,
+    #[serde(default, deserialize_with = "time::deserialize_opt")]
+    pub updated: Option<DateTime<Utc>>
+}
+
+impl<L> Default for OverviewData<L> {
+    fn default() -> OverviewData<L> {
+        OverviewData{
+            lines: Vec::<L>::new(),
+            updated: None::<DateTime<Utc>>
+        }
+    }
+}
+
+impl<L: OverviewLine> Overview for OverviewData<L> {
+    type Line = L;
+
+    fn lines(&self) -> &[L] {
+        &self.lines
+    }
+
+    fn updated(&self) -> Option<DateTime<Utc>> {
+        self.updated
+    }
+
+    fn set_updated(&mut self, updated: DateTime<Utc>) {
+        self.updated = Some(updated);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poe_ninja::currency::{ CurrencyData, CurrencyType };
+    use crate::poe_ninja::general::set_cache_dir;
+
+    /// Each test uses its own league, as the cache directory is global.
+    fn cache_path(league: &League, contents: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("exilian-overview-{}", std::process::id()));
+        set_cache_dir(dir.to_str().unwrap());
+
+        let path = CurrencyData::get_cache_path(league, &CurrencyType::Currency).unwrap();
+        fs::create_dir_all(path.rsplit_once('/').unwrap().0).unwrap();
+        fs::write(&path, contents).unwrap();
+        return path;
+    }
+
+    fn schema_version(path: &str) -> Option<u64> {
+        let value: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        return value["header"]["schema_version"].as_u64();
+    }
+
+    #[test]
+    fn headerless_files_are_migrated() {
+        let league = League::Other("Headerless".to_string());
+        let path = cache_path(&league, r#"{
+            "lines": [], "currencyDetails": [], "updated": "2026-01-02T03:04:05Z"
+        }"#);
+
+        let file = CurrencyData::load_cache(&league, &CurrencyType::Currency).unwrap();
+        assert_eq!(file.header.schema_version, CACHE_SCHEMA_VERSION);
+        assert_eq!(file.header.fetched.to_rfc3339(), "2026-01-02T03:04:05+00:00");
+        assert_eq!(file.header.league, "Headerless");
+        assert_eq!(schema_version(&path), Some(CACHE_SCHEMA_VERSION as u64));
+    }
+
+    #[test]
+    fn version_1_files_are_migrated() {
+        let league = League::Other("Version1".to_string());
+        let path = cache_path(&league, r#"{
+            "header": {
+                "schema_version": 1,
+                "source": "https://poe.ninja/api/data/currencyoverview",
+                "league": "Version1",
+                "type": "Currency",
+                "fetched": "2026-01-02T03:04:05Z"
+            },
+            "data": { "lines": [], "currencyDetails": [], "updated": null }
+        }"#);
+
+        let file = CurrencyData::load_cache(&league, &CurrencyType::Currency).unwrap();
+        assert_eq!(file.header.schema_version, CACHE_SCHEMA_VERSION);
+        assert_eq!(file.header.as_of, None);
+        assert_eq!(file.header.as_of(), file.header.fetched);
+        assert_eq!(schema_version(&path), Some(CACHE_SCHEMA_VERSION as u64));
+    }
+
+    #[test]
+    fn unknown_versions_are_kept() {
+        let league = League::Other("Newer".to_string());
+        let contents = r#"{ "header": { "schema_version": 99 }, "data": {} }"#;
+        let path = cache_path(&league, contents);
+
+        let loaded = CurrencyData::load_cache(&league, &CurrencyType::Currency);
+        assert!(matches!(loaded, Err(Error::CacheVersion(99))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display, fmt::Result as FmtResult, fmt::Formatter,
+    str::FromStr
+};
+
+use crate::error::Error;
+
+use super::general::NextEnum;
+use super::overview::OverviewLine;
+use super::search::{ Fields, MatchMode };
+use super::variant::Selector;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Chaos,
+    Divine,
+    Listings,
+    Change
+}
+
+#[allow(unused)]
+impl SortKey {
+    pub fn from(s: &str) -> Option<SortKey> {
+        match s {
+            "name" => Some(SortKey::Name),
+            "chaos" => Some(SortKey::Chaos),
+            "divine" => Some(SortKey::Divine),
+            "listings" => Some(SortKey::Listings),
+            "change" => Some(SortKey::Change),
+            _ => None::<SortKey>
+        }
+    }
+
+    pub fn show_all() {
+        let mut curr: Option<Self>;
+        let mut i = SortKey::Name;
+
+        println!("Valid Sort Keys");
+        println!("===============");
+        loop {
+            println!("{}", i);
+            curr = i.next();
+            if curr.is_none() { break; }
+            i = curr.unwrap();
+        }
+    }
+
+    /// Names sort A-Z by default, everything else highest first.
+    pub fn descending_by_default(&self) -> bool {
+        *self != SortKey::Name
+    }
+
+    fn compare<L: OverviewLine>(&self, a: &L, b: &L) -> Ordering {
+        match self {
+            SortKey::Name => a.name().cmp(b.name()),
+            SortKey::Chaos => a.chaos_value().total_cmp(&b.chaos_value()),
+            SortKey::Divine => match (a.divine_value(), b.divine_value()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => a.chaos_value().total_cmp(&b.chaos_value())
+            },
+            SortKey::Listings => a.listing_count().cmp(&b.listing_count()),
+            SortKey::Change => a.sparkline().totalChange.total_cmp(&b.sparkline().totalChange)
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<SortKey, Error> {
+        Self::from(s).ok_or_else(|| Error::UnknownType(s.to_string()))
+    }
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            SortKey::Name => write!(f, "name"),
+            SortKey::Chaos => write!(f, "chaos"),
+            SortKey::Divine => write!(f, "divine"),
+            SortKey::Listings => write!(f, "listings"),
+            SortKey::Change => write!(f, "change")
+        }
+    }
+}
+
+impl NextEnum<SortKey> for SortKey {
+    fn next(&self) -> Option<SortKey> {
+        match self {
+            SortKey::Name => Some(SortKey::Chaos),
+            SortKey::Chaos => Some(SortKey::Divine),
+            SortKey::Divine => Some(SortKey::Listings),
+            SortKey::Listings => Some(SortKey::Change),
+            SortKey::Change => None::<SortKey>
+        }
+    }
+}
+
+/// Search, thresholds, ordering and paging for a price listing. Prices are
+/// in chaos. Without a sort key lines stay ranked by search score.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub search: String,
+    pub mode: MatchMode,
+    pub fields: Fields,
+    pub sort: Option<SortKey>,
+    /// Flips the default direction of `sort`.
+    pub reverse: bool,
+    pub min_price: Option<f32>,
+    pub max_price: Option<f32>,
+    pub min_listings: Option<u32>,
+    /// Filters on `OverviewLine::attributes`, e.g. gem level or links.
+    pub select: Selector,
+    pub offset: usize,
+    pub limit: Option<usize>
+}
+
+impl Query {
+    pub fn new(search: &str) -> Query {
+        Query {
+            search: search.to_string(),
+            ..Query::default()
+        }
+    }
+
+    pub fn matches<L: OverviewLine>(&self, line: &L) -> bool {
+        let price = line.chaos_value();
+
+        return self.min_price.is_none_or(|min| price >= min)
+            && self.max_price.is_none_or(|max| price <= max)
+            && self.min_listings.is_none_or(|min| line.listing_count() >= min)
+            && (self.select.is_empty() || self.select.matches(&line.attributes()));
+    }
+
+    /// Filters, sorts and pages `lines`, which are usually the result of a
+    /// search for `self.search`.
+    pub fn apply<L: OverviewLine>(&self, lines: Vec<L>) -> Vec<L> {
+        let mut lines: Vec<L> = lines.into_iter().filter(|line| self.matches(line)).collect();
+
+        if let Some(key) = self.sort {
+            let descending = key.descending_by_default() != self.reverse;
+            lines.sort_by(|a, b| {
+                let ordering = key.compare(a, b);
+                if descending { ordering.reverse() } else { ordering }
+            });
+        }
+
+        return lines.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+    }
+}
@@ -0,0 +1,107 @@
+use std::cmp::Reverse;
+
+use fuzzy_matcher::{ FuzzyMatcher, skim::SkimMatcherV2 };
+
+use regex::{ Regex, RegexBuilder };
+
+use crate::error::Error;
+
+use super::overview::OverviewLine;
+
+/// Scores above any fuzzy score, so exact and prefix hits always rank first.
+const EXACT_SCORE: i64 = 3_000_000;
+const EXACT_IGNORE_CASE_SCORE: i64 = 2_000_000;
+const PREFIX_SCORE: i64 = 1_000_000;
+
+#[derive(Debug, Clone, Default)]
+pub enum MatchMode {
+    #[default]
+    Fuzzy,
+    Exact { ignore_case: bool },
+    Regex(Regex)
+}
+
+impl MatchMode {
+    pub fn regex(pattern: &str, ignore_case: bool) -> Result<MatchMode, Error> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(MatchMode::Regex)
+            .map_err(|e| Error::InvalidPattern(e.to_string()))
+    }
+}
+
+/// Which text of a line is searched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fields {
+    #[default]
+    Name,
+    /// The name plus `OverviewLine::search_fields`.
+    All
+}
+
+fn score_name(name: &str, pattern: &str, mode: &MatchMode, matcher: &SkimMatcherV2) -> Option<i64> {
+    match mode {
+        MatchMode::Fuzzy => {
+            if name == pattern {
+                return Some(EXACT_SCORE);
+            }
+            if name.eq_ignore_ascii_case(pattern) {
+                return Some(EXACT_IGNORE_CASE_SCORE);
+            }
+
+            let fuzzy = matcher.fuzzy_match(name, pattern)?;
+            if name.to_lowercase().starts_with(&pattern.to_lowercase()) {
+                return Some(PREFIX_SCORE + fuzzy);
+            }
+            return Some(fuzzy);
+        },
+        MatchMode::Exact { ignore_case: false } => (name == pattern).then_some(EXACT_SCORE),
+        MatchMode::Exact { ignore_case: true } => {
+            name.eq_ignore_ascii_case(pattern).then_some(EXACT_IGNORE_CASE_SCORE)
+        },
+        MatchMode::Regex(re) => re.find(name).map(|m| {
+            if m.start() == 0 && m.end() == name.len() { EXACT_SCORE } else { PREFIX_SCORE }
+        })
+    }
+}
+
+/// Scores `line` against `pattern`, or `None` when it does not match. Hits
+/// outside the name only count as fuzzy matches at half weight.
+pub fn score<L: OverviewLine>(
+    line: &L, pattern: &str, mode: &MatchMode, fields: Fields, matcher: &SkimMatcherV2
+) -> Option<i64> {
+    let mut best = score_name(line.name(), pattern, mode, matcher);
+    if let Some(trade_id) = line.trade_id() {
+        best = best.max(score_name(trade_id, pattern, mode, matcher));
+    }
+
+    if fields == Fields::All {
+        for text in line.search_fields() {
+            let other = match mode {
+                MatchMode::Fuzzy => matcher.fuzzy_match(text, pattern).map(|s| s / 2),
+                _ => score_name(text, pattern, mode, matcher).map(|s| s / 2)
+            };
+            best = best.max(other);
+        }
+    }
+
+    return best;
+}
+
+/// Every line matching `pattern`, best score first. Ties keep poe.ninja's
+/// order.
+pub fn search<L: OverviewLine>(
+    lines: &[L], pattern: &str, mode: &MatchMode, fields: Fields
+) -> Vec<(i64, L)> {
+    let matcher = SkimMatcherV2::default();
+    let mut results: Vec<(i64, L)> = lines.iter()
+        .filter_map(|line| {
+            score(line, pattern, mode, fields, &matcher).map(|s| (s, line.clone()))
+        })
+        .collect();
+
+    results.sort_by_key(|(score, _)| Reverse(*score));
+
+    return results;
+}
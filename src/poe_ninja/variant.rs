@@ -0,0 +1,165 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::convert::Rate;
+use crate::error::Error;
+
+use super::overview::{ Attributes, OverviewLine };
+
+/// Picks variants of an item by their attributes. Unset criteria match
+/// anything; set ones drop lines without that attribute.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    pub gem_level: Option<u32>,
+    pub gem_quality: Option<u32>,
+    pub corrupted: Option<bool>,
+    pub map_tier: Option<u32>,
+    pub links: Option<u32>,
+    /// Compared ignoring case, e.g. `relic` selects `Relic`.
+    pub variant: Option<String>,
+    pub item_level: Option<u32>
+}
+
+#[allow(unused)]
+impl Selector {
+    /// Parses terms such as `level=4 quality=20 corrupted`.
+    pub fn parse(terms: &[String]) -> Result<Selector, Error> {
+        let mut selector = Selector::default();
+
+        for term in terms {
+            selector.add(term)?;
+        }
+        return Ok(selector);
+    }
+
+    /// True for the terms `add` accepts, as opposed to a search string.
+    pub fn is_term(s: &str) -> bool {
+        s.contains('=') || s == "corrupted"
+    }
+
+    /// Sets the criterion named by one `key=value` term. A bare `corrupted`
+    /// is `corrupted=true`.
+    pub fn add(&mut self, term: &str) -> Result<(), Error> {
+        let (key, value) = term.split_once('=').unwrap_or((term, "true"));
+        let invalid = || Error::InvalidSelector(term.to_string());
+        let number = || value.trim().parse::<u32>().map_err(|_| invalid());
+
+        match key.trim() {
+            "level" | "gem-level" => self.gem_level = Some(number()?),
+            "quality" => self.gem_quality = Some(number()?),
+            "tier" => self.map_tier = Some(number()?),
+            "links" => self.links = Some(number()?),
+            "ilvl" | "item-level" => self.item_level = Some(number()?),
+            "variant" => self.variant = Some(value.trim().to_string()),
+            "corrupted" => {
+                self.corrupted = Some(value.trim().parse::<bool>().map_err(|_| invalid())?);
+            },
+            _ => return Err(invalid())
+        }
+        return Ok(());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Selector::default()
+    }
+
+    pub fn matches(&self, attributes: &Attributes) -> bool {
+        let equals = |wanted: Option<u32>, actual: Option<u32>| {
+            wanted.is_none() || wanted == actual
+        };
+        let variant = match (&self.variant, &attributes.variant) {
+            (None, _) => true,
+            (Some(wanted), Some(actual)) => wanted.eq_ignore_ascii_case(actual),
+            (Some(_), None) => false
+        };
+        // poe.ninja leaves `corrupted` out for uncorrupted lines.
+        let corrupted = self.corrupted.is_none_or(|wanted| {
+            wanted == attributes.corrupted.unwrap_or(false)
+        });
+
+        return variant
+            && corrupted
+            && equals(self.gem_level, attributes.gem_level)
+            && equals(self.gem_quality, attributes.gem_quality)
+            && equals(self.map_tier, attributes.map_tier)
+            && equals(self.links, attributes.links)
+            && equals(self.item_level, attributes.item_level);
+    }
+}
+
+/// All lines sharing a name, e.g. every level and quality of a gem.
+#[derive(Debug, Clone)]
+pub struct VariantGroup<L: OverviewLine> {
+    pub name: String,
+    pub lines: Vec<L>
+}
+
+#[allow(unused)]
+impl<L: OverviewLine> VariantGroup<L> {
+    /// Groups `lines` by name, keeping the order names first appear in.
+    pub fn group(lines: Vec<L>) -> Vec<VariantGroup<L>> {
+        let mut groups = Vec::<VariantGroup<L>>::new();
+
+        for line in lines {
+            match groups.iter_mut().find(|group| group.name == line.name()) {
+                Some(group) => group.lines.push(line),
+                None => groups.push(VariantGroup {
+                    name: line.name().to_string(),
+                    lines: vec![line]
+                })
+            }
+        }
+        return groups;
+    }
+
+    pub fn select(&self, selector: &Selector) -> Vec<&L> {
+        self.lines.iter().filter(|line| selector.matches(&line.attributes())).collect()
+    }
+
+    /// Cheapest and dearest chaos value across the variants.
+    pub fn price_range(&self) -> (f32, f32) {
+        self.lines.iter()
+            .map(|line| line.chaos_value())
+            .fold((f32::MAX, f32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)))
+    }
+
+    /// The name with the price range, then one indented line per variant.
+    pub fn render(&self, unit: &Rate) -> String {
+        let price = |chaos: f32| -> String {
+            if unit.chaos == 1.0 {
+                format!("{}c", chaos)
+            } else {
+                format!("{:.2} {}", unit.from_chaos(chaos), unit.name)
+            }
+        };
+        let (lo, hi) = self.price_range();
+        let range = if lo == hi {
+            price(lo)
+        } else {
+            format!("{} - {}", price(lo), price(hi))
+        };
+
+        let count = match self.lines.len() {
+            1 => "1 variant".to_string(),
+            n => format!("{} variants", n)
+        };
+
+        let mut out = vec![format!("{}: {} ({})", self.name, range, count)];
+        for line in &self.lines {
+            let label = match line.attributes().to_string() {
+                attributes if attributes.is_empty() => "base".to_string(),
+                attributes => attributes
+            };
+
+            out.push(format!(
+                "  {}: {} ({} listed)", label, price(line.chaos_value()), line.listing_count()
+            ));
+        }
+        return out.join("\n");
+    }
+}
+
+impl<L: OverviewLine> Display for VariantGroup<L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.render(&Rate::chaos()))
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::RwLock;
+
+use chrono::{ DateTime, Local, Utc };
+
+use serde::{ Deserialize, Deserializer };
+
+/// Format `updated` was written in before timestamps were stored as UTC.
+const LEGACY_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f%:z";
+
+/// Time zone timestamps are shown in. They are always stored as UTC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Zone {
+    #[default]
+    Local,
+    Utc
+}
+
+static ZONE: RwLock<Zone> = RwLock::new(Zone::Local);
+
+pub fn set_zone(zone: Zone) {
+    if let Ok(mut current) = ZONE.write() {
+        *current = zone;
+    }
+}
+
+pub fn zone() -> Zone {
+    ZONE.read().map_or(Zone::Local, |zone| *zone)
+}
+
+/// `time` as `2024-04-25 10:44:45 +02:00` in the zone chosen with `set_zone`.
+pub fn display(time: &DateTime<Utc>) -> String {
+    match zone() {
+        Zone::Local => time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S %:z").to_string(),
+        Zone::Utc => time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    }
+}
+
+/// Parses RFC 3339, or the `Local::now().to_string()` form older caches
+/// contain.
+pub fn parse(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, LEGACY_FORMAT))
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Deserializes an optional timestamp with `parse`, turning unreadable ones
+/// into `None`. Use with `#[serde(deserialize_with)]`.
+pub fn deserialize_opt<'de, D: Deserializer<'de>>(
+    deserializer: D
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    let s = Option::<String>::deserialize(deserializer)?;
+
+    return Ok(s.as_deref().and_then(parse));
+}